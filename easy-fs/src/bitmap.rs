@@ -0,0 +1,91 @@
+use super::{get_block_cache, BlockDevice, BLOCK_SZ};
+use alloc::sync::Arc;
+
+/// A block of bitmap bits, represented as 64-bit words for fast scanning.
+type BitmapBlock = [u64; BLOCK_SZ / 8];
+
+/// Number of bits held in a single bitmap block.
+const BLOCK_BITS: usize = BLOCK_SZ * 8;
+
+/// An on-disk bitmap spanning `blocks` consecutive blocks starting at
+/// `start_block_id`, used to track free inodes and free data blocks.
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+}
+
+/// Decompose a bit index into (block index within the bitmap, u64 word
+/// index within the block, bit index within the word).
+fn decomposition(mut bit: usize) -> (usize, usize, usize) {
+    let block_pos = bit / BLOCK_BITS;
+    bit %= BLOCK_BITS;
+    (block_pos, bit / 64, bit % 64)
+}
+
+impl Bitmap {
+    /// Create a new bitmap covering `[start_block_id, start_block_id + blocks)`
+    pub fn new(start_block_id: usize, blocks: usize) -> Self {
+        Self {
+            start_block_id,
+            blocks,
+        }
+    }
+
+    /// Allocate the first unset bit, returning its bit index, or `None` if
+    /// the bitmap is full.
+    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
+        for block_id in 0..self.blocks {
+            let pos = get_block_cache(block_id + self.start_block_id, Arc::clone(block_device))
+                .lock()
+                .modify(0, |bitmap_block: &mut BitmapBlock| {
+                    if let Some((bits64_pos, inner_pos)) = bitmap_block
+                        .iter()
+                        .enumerate()
+                        .find(|(_, bits64)| **bits64 != u64::MAX)
+                        .map(|(bits64_pos, bits64)| (bits64_pos, bits64.trailing_ones() as usize))
+                    {
+                        bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                        Some(block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos)
+                    } else {
+                        None
+                    }
+                });
+            if pos.is_some() {
+                return pos;
+            }
+        }
+        None
+    }
+
+    /// Clear the bit at `bit`, marking it free again.
+    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
+        let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+        get_block_cache(block_pos + self.start_block_id, Arc::clone(block_device))
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                assert!(bitmap_block[bits64_pos] & (1u64 << inner_pos) > 0);
+                bitmap_block[bits64_pos] -= 1u64 << inner_pos;
+            });
+    }
+
+    /// Total number of bits this bitmap can track.
+    pub fn maximum(&self) -> usize {
+        self.blocks * BLOCK_BITS
+    }
+
+    /// Number of bits still unset (free).
+    pub fn count_free(&self, block_device: &Arc<dyn BlockDevice>) -> usize {
+        (0..self.blocks)
+            .map(|block_id| {
+                get_block_cache(block_id + self.start_block_id, Arc::clone(block_device))
+                    .lock()
+                    .read(0, |bitmap_block: &BitmapBlock| {
+                        bitmap_block
+                            .iter()
+                            .map(|bits64| bits64.count_zeros() as usize)
+                            .sum::<usize>()
+                    })
+            })
+            .sum()
+    }
+}
@@ -0,0 +1,48 @@
+use super::{BlockDevice, DirEntry, DiskInode, DIRENT_SZ};
+use alloc::sync::Arc;
+
+/// Iterates the `(index, DirEntry)` pairs stored in a directory's
+/// `DiskInode`, reading one `DIRENT_SZ` record at a time instead of
+/// re-scanning the whole directory by hand at each call site.
+pub struct DirEntryIter<'a> {
+    disk_inode: &'a DiskInode,
+    block_device: &'a Arc<dyn BlockDevice>,
+    index: usize,
+    count: usize,
+}
+
+impl<'a> DirEntryIter<'a> {
+    /// Iterate every entry currently stored in `disk_inode`, which must be
+    /// a directory.
+    pub fn new(disk_inode: &'a DiskInode, block_device: &'a Arc<dyn BlockDevice>) -> Self {
+        assert!(disk_inode.is_dir());
+        Self {
+            disk_inode,
+            block_device,
+            index: 0,
+            count: disk_inode.size as usize / DIRENT_SZ,
+        }
+    }
+}
+
+impl Iterator for DirEntryIter<'_> {
+    type Item = (usize, DirEntry);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+        let mut dirent = DirEntry::empty();
+        assert_eq!(
+            self.disk_inode.read_at(
+                self.index * DIRENT_SZ,
+                dirent.as_bytes_mut(),
+                self.block_device,
+            ),
+            DIRENT_SZ,
+        );
+        let item = (self.index, dirent);
+        self.index += 1;
+        Some(item)
+    }
+}
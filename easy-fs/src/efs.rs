@@ -0,0 +1,130 @@
+use super::{
+    get_block_cache, block_cache_sync_all, Bitmap, BlockDevice, DiskInode, DiskInodeType, Inode,
+    StatFs, BLOCK_SZ,
+};
+use alloc::sync::Arc;
+use spin::Mutex;
+
+/// Size, in bytes, of a `DiskInode` slot in the inode area.
+const INODE_SIZE: usize = 128;
+const INODES_PER_BLOCK: usize = BLOCK_SZ / INODE_SIZE;
+
+/// An easy file system on a block device.
+///
+/// Layout: super block, inode bitmap, inode area, data bitmap, data area.
+pub struct EasyFileSystem {
+    /// The block device this filesystem is backed by
+    pub block_device: Arc<dyn BlockDevice>,
+    /// Tracks free/used inode slots
+    pub inode_bitmap: Bitmap,
+    /// Tracks free/used data blocks
+    pub data_bitmap: Bitmap,
+    inode_area_start_block: u32,
+    data_area_start_block: u32,
+    /// Real size of the data area, in blocks. `data_bitmap` is allocated in
+    /// whole 4096-bit chunks, so its `maximum()` over-provisions past this.
+    data_area_blocks: u32,
+}
+
+impl EasyFileSystem {
+    /// Create a filesystem spanning `total_blocks`, with an inode bitmap of
+    /// `inode_bitmap_blocks` blocks and an inode area sized to hold every
+    /// inode that bitmap can track.
+    pub fn create(
+        block_device: Arc<dyn BlockDevice>,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+    ) -> Arc<Mutex<Self>> {
+        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize);
+        let inode_num = inode_bitmap.maximum();
+        let inode_area_blocks = (inode_num * INODE_SIZE).div_ceil(BLOCK_SZ) as u32;
+        let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        let data_bitmap_blocks = (data_total_blocks + 4096).div_ceil(4096);
+        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        let data_bitmap = Bitmap::new(
+            (1 + inode_total_blocks) as usize,
+            data_bitmap_blocks as usize,
+        );
+        let mut efs = Self {
+            block_device: Arc::clone(&block_device),
+            inode_bitmap,
+            data_bitmap,
+            inode_area_start_block: 1 + inode_bitmap_blocks,
+            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            data_area_blocks,
+        };
+        // clear every block
+        for i in 0..total_blocks {
+            get_block_cache(i as usize, Arc::clone(&block_device))
+                .lock()
+                .modify(0, |block: &mut [u8; BLOCK_SZ]| block.fill(0));
+        }
+        // root directory
+        assert_eq!(efs.alloc_inode(), 0);
+        let (root_block_id, root_block_offset) = efs.get_disk_inode_pos(0);
+        get_block_cache(root_block_id as usize, Arc::clone(&block_device))
+            .lock()
+            .modify(root_block_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.initialize(DiskInodeType::Directory);
+            });
+        block_cache_sync_all();
+        Arc::new(Mutex::new(efs))
+    }
+
+    /// Locate the `(block_id, offset)` of the `inode_id`'th inode slot.
+    pub fn get_disk_inode_pos(&self, inode_id: u32) -> (u32, usize) {
+        let inode_block = inode_id as usize / INODES_PER_BLOCK;
+        let offset = inode_id as usize % INODES_PER_BLOCK * INODE_SIZE;
+        (self.inode_area_start_block + inode_block as u32, offset)
+    }
+
+    /// Allocate an inode slot, returning its id.
+    pub fn alloc_inode(&mut self) -> u32 {
+        self.inode_bitmap.alloc(&self.block_device).unwrap() as u32
+    }
+
+    /// Free an inode slot.
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap
+            .dealloc(&self.block_device, inode_id as usize);
+    }
+
+    /// Allocate a data block, returning its block id on the device.
+    pub fn alloc_data(&mut self) -> u32 {
+        self.data_bitmap.alloc(&self.block_device).unwrap() as u32 + self.data_area_start_block
+    }
+
+    /// Free a data block previously returned by `alloc_data`.
+    pub fn dealloc_data(&mut self, block_id: u32) {
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(0, |block: &mut [u8; BLOCK_SZ]| block.fill(0));
+        self.data_bitmap
+            .dealloc(&self.block_device, (block_id - self.data_area_start_block) as usize);
+    }
+
+    /// The root directory inode
+    pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
+        let block_device = efs.lock().block_device.clone();
+        let (block_id, block_offset) = efs.lock().get_disk_inode_pos(0);
+        Inode::new(block_id, block_offset, Arc::clone(efs), block_device, 0)
+    }
+
+    /// Free/total block and inode counts, computed by scanning the bitmaps
+    /// rather than tracking a running counter, so the numbers stay correct
+    /// even after a crash that could have left a counter stale.
+    pub fn statfs(&self) -> StatFs {
+        StatFs {
+            block_size: BLOCK_SZ as u32,
+            blocks: self.data_area_blocks,
+            // bits past `data_area_blocks` are never backed by a block, but
+            // still read as free in the bitmap
+            blocks_free: self.data_bitmap.count_free(&self.block_device).saturating_sub(
+                self.data_bitmap.maximum() - self.data_area_blocks as usize,
+            ) as u32,
+            inodes: self.inode_bitmap.maximum() as u32,
+            inodes_free: self.inode_bitmap.count_free(&self.block_device) as u32,
+        }
+    }
+}
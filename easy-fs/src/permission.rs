@@ -0,0 +1,87 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// The access an inode's owner/group/other rwx bits, plus the setuid,
+    /// setgid and sticky bits, packed the same way a POSIX mode word is.
+    #[derive(Default)]
+    pub struct Mode: u16 {
+        const OTHER_X = 0o0001;
+        const OTHER_W = 0o0002;
+        const OTHER_R = 0o0004;
+        const GROUP_X = 0o0010;
+        const GROUP_W = 0o0020;
+        const GROUP_R = 0o0040;
+        const OWNER_X = 0o0100;
+        const OWNER_W = 0o0200;
+        const OWNER_R = 0o0400;
+        const STICKY  = 0o1000;
+        const SETGID  = 0o2000;
+        const SETUID  = 0o4000;
+    }
+}
+
+impl Mode {
+    /// A plain `rw-r--r--` file
+    pub const DEFAULT_FILE: Mode = Mode::OWNER_R
+        .union(Mode::OWNER_W)
+        .union(Mode::GROUP_R)
+        .union(Mode::OTHER_R);
+    /// A plain `rwxr-xr-x` directory
+    pub const DEFAULT_DIR: Mode = Mode::OWNER_R
+        .union(Mode::OWNER_W)
+        .union(Mode::OWNER_X)
+        .union(Mode::GROUP_R)
+        .union(Mode::GROUP_X)
+        .union(Mode::OTHER_R)
+        .union(Mode::OTHER_X);
+    /// A `rwxrwxrwx` symlink; like Linux, its mode is cosmetic since
+    /// access checks follow the link to the target's own mode.
+    pub const DEFAULT_SYMLINK: Mode = Mode::OWNER_R
+        .union(Mode::OWNER_W)
+        .union(Mode::OWNER_X)
+        .union(Mode::GROUP_R)
+        .union(Mode::GROUP_W)
+        .union(Mode::GROUP_X)
+        .union(Mode::OTHER_R)
+        .union(Mode::OTHER_W)
+        .union(Mode::OTHER_X);
+}
+
+bitflags! {
+    /// The access an operation is requesting, mirrored against a `Mode`'s
+    /// owner/group/other triplet depending on who is asking.
+    #[derive(Default)]
+    pub struct Access: u8 {
+        const READ = 0b100;
+        const WRITE = 0b010;
+        const EXEC = 0b001;
+    }
+}
+
+/// uid 0 is root and bypasses every permission check, matching POSIX.
+pub const ROOT_UID: u32 = 0;
+
+/// Mirror the owner/group/other rwx test a POSIX-compliant filesystem runs:
+/// owner bits if `uid` matches, else group bits if `gid` or one of
+/// `groups` matches, else other bits. Root always passes.
+pub fn check_access(
+    mode: Mode,
+    owner_uid: u32,
+    owner_gid: u32,
+    uid: u32,
+    gid: u32,
+    groups: &[u32],
+    want: Access,
+) -> bool {
+    if uid == ROOT_UID {
+        return true;
+    }
+    let granted = if uid == owner_uid {
+        mode.bits() >> 6
+    } else if gid == owner_gid || groups.contains(&owner_gid) {
+        mode.bits() >> 3
+    } else {
+        mode.bits()
+    } & 0o7;
+    (granted & want.bits() as u16) == want.bits() as u16
+}
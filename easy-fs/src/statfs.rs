@@ -0,0 +1,19 @@
+//! Free-space reporting, modeled on `ReplyStatfs`: computed on demand from
+//! the bitmaps rather than a running counter, so it stays accurate across
+//! crashes that might have left an in-memory counter stale.
+
+/// Snapshot of how full a filesystem is, as returned by
+/// [`crate::EasyFileSystem::statfs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StatFs {
+    /// Size of a block, in bytes
+    pub block_size: u32,
+    /// Total number of data blocks
+    pub blocks: u32,
+    /// Number of unallocated data blocks
+    pub blocks_free: u32,
+    /// Total number of inodes
+    pub inodes: u32,
+    /// Number of unallocated inodes
+    pub inodes_free: u32,
+}
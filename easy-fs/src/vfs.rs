@@ -1,7 +1,9 @@
 use super::{
-    block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
-    EasyFileSystem, DIRENT_SZ,
+    block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DirEntryIter, DiskInode,
+    DiskInodeType, EasyFileSystem, BLOCK_SZ, DIRENT_SZ,
 };
+use crate::permission::{Access, Mode};
+use crate::xattr;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
@@ -47,20 +49,9 @@ impl Inode {
     }
 
     fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
-        // assert it is a directory
-        assert!(disk_inode.is_dir());
-        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
-        let mut dirent = DirEntry::empty();
-        for i in 0..file_count {
-            assert_eq!(
-                disk_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device,),
-                DIRENT_SZ,
-            );
-            if dirent.name() == name {
-                return Some(dirent.inode_id() as u32);
-            }
-        }
-        None
+        DirEntryIter::new(disk_inode, &self.block_device)
+            .find(|(_, dirent)| dirent.name() == name)
+            .map(|(_, dirent)| dirent.inode_id())
     }
 
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
@@ -97,6 +88,14 @@ impl Inode {
     }
 
     pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_owned(name, 0, 0, 0)
+    }
+
+    /// Like [`Inode::create`], but the new file is owned by `uid`/`gid`
+    /// instead of defaulting to root, for callers that know the requesting
+    /// task's identity (e.g. `open_file` with `O_CREAT`). `now_us` seeds
+    /// atime/mtime/ctime; pass `timer::get_time_us()` from the `os` crate.
+    pub fn create_owned(&self, name: &str, uid: u32, gid: u32, now_us: u64) -> Option<Arc<Inode>> {
         let mut fs = self.fs.lock();
         let op = |root_inode: &mut DiskInode| {
             // assert it is a directory
@@ -116,6 +115,9 @@ impl Inode {
             .lock()
             .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
                 new_inode.initialize(DiskInodeType::File);
+                new_inode.set_owner(uid, gid, Mode::DEFAULT_FILE);
+                new_inode.touch_atime(now_us);
+                new_inode.touch_mtime(now_us);
             });
         self.modify_disk_inode(|root_inode| {
             // append file in the dirent
@@ -145,28 +147,87 @@ impl Inode {
         // release efs lock automatically by compiler
     }
 
+    /// Create a symlink named `link_name` in this directory pointing at
+    /// `target`. The target path is stored verbatim in the new inode's data
+    /// blocks, the same way a regular file's content is.
+    pub fn symlink_at(&self, link_name: &str, target: &str) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        let op = |root_inode: &mut DiskInode| {
+            assert!(root_inode.is_dir());
+            self.find_inode_id(link_name, root_inode)
+        };
+        if self.modify_disk_inode(op).is_some() {
+            return None;
+        }
+
+        let new_inode_id = fs.alloc_inode();
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                new_inode.initialize(DiskInodeType::Symlink);
+            });
+
+        let new_inode = Arc::new(Self::new(
+            new_inode_block_id,
+            new_inode_block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+            new_inode_id,
+        ));
+        // `fs` is already held, so go through the disk inode directly
+        // rather than `Inode::write_at`, which would lock it again.
+        new_inode.modify_disk_inode(|disk_inode| {
+            self.increase_size(target.len() as u32, disk_inode, &mut fs);
+            disk_inode.write_at(0, target.as_bytes(), &self.block_device);
+        });
+
+        self.append_dirent(link_name, new_inode_id, &mut fs);
+
+        block_cache_sync_all();
+        Some(new_inode)
+    }
+
+    /// Read back the target path stored by [`Inode::symlink_at`], or `None`
+    /// if this inode is not a symlink.
+    pub fn readlink(&self) -> Option<String> {
+        if !self.is_symlink() {
+            return None;
+        }
+        let size = self.read_disk_inode(|disk_inode| disk_inode.size as usize);
+        let mut buf = alloc::vec![0u8; size];
+        self.read_at(0, &mut buf);
+        Some(String::from_utf8_lossy(&buf).into_owned())
+    }
+
     pub fn ls(&self) -> Vec<String> {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
-            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
-            let mut v: Vec<String> = Vec::new();
-            for i in 0..file_count {
-                let mut dirent = DirEntry::empty();
-                assert_eq!(
-                    disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device,),
-                    DIRENT_SZ,
-                );
-                v.push(String::from(dirent.name()));
-            }
-            v
+            DirEntryIter::new(disk_inode, &self.block_device)
+                .map(|(_, dirent)| String::from(dirent.name()))
+                .collect()
         })
     }
 
+    /// Read without touching atime. Use [`Inode::read_at_touched`] when a
+    /// real timestamp is available.
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
     }
 
+    /// Like [`Inode::read_at`], but also refreshes atime to `now_us`
+    /// (microseconds, see `timer::get_time_us` in the `os` crate).
+    pub fn read_at_touched(&self, offset: usize, buf: &mut [u8], now_us: u64) -> usize {
+        let _fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            disk_inode.touch_atime(now_us);
+            disk_inode.read_at(offset, buf, &self.block_device)
+        })
+    }
+
+    /// Write without touching mtime/ctime. Use [`Inode::write_at_touched`]
+    /// when a real timestamp is available.
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
         let mut fs = self.fs.lock();
         let size = self.modify_disk_inode(|disk_inode| {
@@ -177,6 +238,20 @@ impl Inode {
         size
     }
 
+    /// Like [`Inode::write_at`], but also refreshes mtime/ctime to `now_us`.
+    pub fn write_at_touched(&self, offset: usize, buf: &[u8], now_us: u64) -> usize {
+        let mut fs = self.fs.lock();
+        let size = self.modify_disk_inode(|disk_inode| {
+            self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
+            disk_inode.touch_mtime(now_us);
+            disk_inode.write_at(offset, buf, &self.block_device)
+        });
+        block_cache_sync_all();
+        size
+    }
+
+    /// Clear without touching mtime/ctime. Use [`Inode::clear_touched`] when
+    /// a real timestamp is available.
     pub fn clear(&self) {
         let mut fs = self.fs.lock();
         self.modify_disk_inode(|disk_inode| {
@@ -189,6 +264,21 @@ impl Inode {
         });
         block_cache_sync_all();
     }
+
+    /// Like [`Inode::clear`], but also refreshes mtime/ctime to `now_us`.
+    pub fn clear_touched(&self, now_us: u64) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            let size = disk_inode.size;
+            let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
+            assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
+            for data_block in data_blocks_dealloc.into_iter() {
+                fs.dealloc_data(data_block);
+            }
+            disk_inode.touch_mtime(now_us);
+        });
+        block_cache_sync_all();
+    }
 }
 
 impl Inode {
@@ -200,6 +290,10 @@ impl Inode {
     pub fn is_file(&self) -> bool {
         self.read_disk_inode(|disk_inode| disk_inode.is_file())
     }
+    /// Whether this inode is a symlink
+    pub fn is_symlink(&self) -> bool {
+        self.read_disk_inode(|disk_inode| disk_inode.is_symlink())
+    }
     /// Get hard link count
     pub fn links_count(&self) -> u32 {
         self.read_disk_inode(|disk_inode| disk_inode.links_count())
@@ -209,22 +303,28 @@ impl Inode {
         self.inode_id
     }
 
+    /// Owning (uid, gid) of this inode
+    pub fn owner(&self) -> (u32, u32) {
+        self.read_disk_inode(|disk_inode| (disk_inode.uid, disk_inode.gid))
+    }
+
+    /// This inode's mode bits
+    pub fn mode(&self) -> Mode {
+        self.read_disk_inode(|disk_inode| disk_inode.mode)
+    }
+
+    /// Test whether `(uid, gid, groups)` may perform `want` on this inode,
+    /// mirroring the owner/group/other POSIX check (root always passes).
+    pub fn check_access(&self, uid: u32, gid: u32, groups: &[u32], want: Access) -> bool {
+        self.read_disk_inode(|disk_inode| disk_inode.check_access(uid, gid, groups, want))
+    }
+
     /// Return Some(inode_id, index in dir_disk_inode)
     fn find_entry_inode_id_and_index(&self, name: &str) -> Option<(u32, usize)> {
         self.read_disk_inode(|dir_disk_inode| {
-            assert!(dir_disk_inode.is_dir());
-
-            let file_count = (dir_disk_inode.size as usize) / DIRENT_SZ;
-            let mut entry = DirEntry::empty();
-            for idx in 0..file_count {
-                let offset = idx * DIRENT_SZ;
-                dir_disk_inode.read_at(offset, entry.as_bytes_mut(), &self.block_device);
-
-                if entry.name() == name {
-                    return Some((entry.inode_id(), idx));
-                }
-            }
-            None
+            DirEntryIter::new(dir_disk_inode, &self.block_device)
+                .find(|(_, entry)| entry.name() == name)
+                .map(|(idx, entry)| (entry.inode_id(), idx))
         })
     }
 
@@ -271,6 +371,16 @@ impl Inode {
 
     /// Create new link
     pub fn link_at(&self, old_path: &str, new_path: &str) -> Option<Arc<Inode>> {
+        self.link_at_touched(old_path, new_path, 0)
+    }
+
+    /// Like [`Inode::link_at`], but also refreshes the target's ctime.
+    pub fn link_at_touched(
+        &self,
+        old_path: &str,
+        new_path: &str,
+        now_us: u64,
+    ) -> Option<Arc<Inode>> {
         let mut fs = self.fs.lock();
 
         let (inode_id, _) = self.find_entry_inode_id_and_index(old_path)?;
@@ -278,7 +388,10 @@ impl Inode {
         let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
         get_block_cache(block_id as usize, Arc::clone(&self.block_device))
             .lock()
-            .modify(block_offset, DiskInode::new_link);
+            .modify(block_offset, |disk_inode| {
+                disk_inode.new_link();
+                disk_inode.touch_ctime(now_us);
+            });
 
         self.append_dirent(new_path, inode_id, &mut fs);
 
@@ -296,6 +409,11 @@ impl Inode {
 
     /// Remove inode under current inode by name
     pub fn unlink(&self, name: &str) -> Option<Arc<Inode>> {
+        self.unlink_touched(name, 0)
+    }
+
+    /// Like [`Inode::unlink`], but also refreshes the target's ctime.
+    pub fn unlink_touched(&self, name: &str, now_us: u64) -> Option<Arc<Inode>> {
         let fs = self.fs.lock();
 
         let (inode_id, idx) = self.find_entry_inode_id_and_index(name)?;
@@ -303,7 +421,10 @@ impl Inode {
         let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
         get_block_cache(block_id as usize, Arc::clone(&self.block_device))
             .lock()
-            .modify(block_offset, DiskInode::unlink);
+            .modify(block_offset, |disk_inode| {
+                disk_inode.unlink();
+                disk_inode.touch_ctime(now_us);
+            });
 
         self.swap_remove_dirent(idx);
 
@@ -339,4 +460,80 @@ impl Inode {
 
         block_cache_sync_all();
     }
+
+    /// Read every packed record out of this inode's xattr block, or an
+    /// empty list if none has been allocated yet.
+    fn read_xattr_records(&self) -> Vec<(String, Vec<u8>)> {
+        let xattr_block = self.read_disk_inode(|disk_inode| disk_inode.xattr);
+        if xattr_block == 0 {
+            return Vec::new();
+        }
+        get_block_cache(xattr_block as usize, Arc::clone(&self.block_device))
+            .lock()
+            .read(0, |block: &[u8; BLOCK_SZ]| xattr::decode(block))
+    }
+
+    /// Allocate the xattr block on first use and write `records` back into
+    /// it, packed. Fails without allocating or modifying anything if
+    /// `records` doesn't fit in a single block.
+    fn write_xattr_records(&self, records: &[(String, Vec<u8>)]) -> bool {
+        let mut encoded = [0u8; BLOCK_SZ];
+        if !xattr::encode(records, &mut encoded) {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        let xattr_block = self.modify_disk_inode(|disk_inode| {
+            if disk_inode.xattr == 0 {
+                disk_inode.xattr = fs.alloc_data();
+            }
+            disk_inode.xattr
+        });
+        get_block_cache(xattr_block as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(0, |block: &mut [u8; BLOCK_SZ]| block.copy_from_slice(&encoded));
+        block_cache_sync_all();
+        true
+    }
+
+    /// Set the xattr named `name` to `value`, overwriting any previous
+    /// value for the same name. Returns `false` without changing anything
+    /// if the new set of records no longer fits in a single xattr block.
+    pub fn set_xattr(&self, name: &str, value: &[u8]) -> bool {
+        let mut records = self.read_xattr_records();
+        if let Some(existing) = records.iter_mut().find(|(n, _)| n == name) {
+            existing.1 = value.to_vec();
+        } else {
+            records.push((String::from(name), value.to_vec()));
+        }
+        self.write_xattr_records(&records)
+    }
+
+    /// Look up the xattr named `name`.
+    pub fn get_xattr(&self, name: &str) -> Option<Vec<u8>> {
+        self.read_xattr_records()
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// List every xattr name set on this inode.
+    pub fn list_xattr(&self) -> Vec<String> {
+        self.read_xattr_records()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect()
+    }
+
+    /// Remove the xattr named `name`, compacting the remaining records so
+    /// the block stays dense (no tombstones).
+    pub fn remove_xattr(&self, name: &str) {
+        let mut records = self.read_xattr_records();
+        records.retain(|(n, _)| n != name);
+        self.write_xattr_records(&records);
+    }
+
+    /// Report how full the filesystem backing this inode is.
+    pub fn stat_fs(&self) -> crate::StatFs {
+        self.fs.lock().statfs()
+    }
 }
@@ -0,0 +1,74 @@
+//! Packed key/value record format used by the single xattr block an inode
+//! may lazily allocate, one record per `(name, value)` pair:
+//!
+//! ```text
+//! | name_len: u16 | name: [u8; name_len] | value_len: u16 | value: [u8; value_len] | ...
+//! ```
+//!
+//! The list ends at the first `name_len == 0` record or when the block is
+//! exhausted, whichever comes first.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Decode every record packed into `block`.
+pub fn decode(block: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut records = Vec::new();
+    let mut pos = 0usize;
+    loop {
+        if pos + 2 > block.len() {
+            break;
+        }
+        let name_len = u16::from_le_bytes([block[pos], block[pos + 1]]) as usize;
+        if name_len == 0 {
+            break;
+        }
+        pos += 2;
+        if pos + name_len + 2 > block.len() {
+            break;
+        }
+        let name = String::from_utf8_lossy(&block[pos..pos + name_len]).into_owned();
+        pos += name_len;
+        let value_len = u16::from_le_bytes([block[pos], block[pos + 1]]) as usize;
+        pos += 2;
+        if pos + value_len > block.len() {
+            break;
+        }
+        let value = block[pos..pos + value_len].to_vec();
+        pos += value_len;
+        records.push((name, value));
+    }
+    records
+}
+
+/// Pack `records` back into `block`, zeroing the remainder so the next
+/// `decode` stops at the right place. Returns `false` without touching
+/// `block` if `records` doesn't fit, leaving the previous contents intact —
+/// a `set_xattr` that would overflow the block is the caller's to reject,
+/// not a reason to panic on ordinary userspace input.
+pub fn encode(records: &[(String, Vec<u8>)], block: &mut [u8]) -> bool {
+    let encoded_len: usize = records
+        .iter()
+        .map(|(name, value)| 2 + name.as_bytes().len() + 2 + value.len())
+        .sum();
+    if encoded_len > block.len() {
+        return false;
+    }
+
+    block.fill(0);
+    let mut pos = 0usize;
+    for (name, value) in records {
+        let name_bytes = name.as_bytes();
+        let name_len = name_bytes.len();
+        let value_len = value.len();
+        block[pos..pos + 2].copy_from_slice(&(name_len as u16).to_le_bytes());
+        pos += 2;
+        block[pos..pos + name_len].copy_from_slice(name_bytes);
+        pos += name_len;
+        block[pos..pos + 2].copy_from_slice(&(value_len as u16).to_le_bytes());
+        pos += 2;
+        block[pos..pos + value_len].copy_from_slice(value);
+        pos += value_len;
+    }
+    true
+}
@@ -10,6 +10,8 @@ bitflags! {
         const DIR   = 0o040000;
         /// ordinary regular file
         const FILE  = 0o100000;
+        /// symbolic link
+        const SYMLINK = 0o120000;
     }
 }
 
@@ -25,8 +27,14 @@ pub struct Stat {
     pub mode: StatMode,
     /// number of hard links
     pub nlink: u32,
+    /// last access time, in microseconds since boot
+    pub atime: u64,
+    /// last content modification time, in microseconds since boot
+    pub mtime: u64,
+    /// last metadata change time, in microseconds since boot
+    pub ctime: u64,
     /// unused pad
-    pad: [u64; 7],
+    pad: [u64; 4],
 }
 
 /// File Status
@@ -38,6 +46,12 @@ pub struct FileStatus {
     pub mode: StatMode,
     /// number of hard links
     pub num_links: u32,
+    /// last access time, in microseconds since boot
+    pub atime: u64,
+    /// last content modification time, in microseconds since boot
+    pub mtime: u64,
+    /// last metadata change time, in microseconds since boot
+    pub ctime: u64,
 }
 
 impl From<FileStatus> for Stat {
@@ -46,6 +60,9 @@ impl From<FileStatus> for Stat {
             inumber: ino,
             mode,
             num_links: nlink,
+            atime,
+            mtime,
+            ctime,
         }: FileStatus,
     ) -> Stat {
         Stat {
@@ -53,7 +70,10 @@ impl From<FileStatus> for Stat {
             ino,
             mode,
             nlink,
-            pad: [0; 7],
+            atime,
+            mtime,
+            ctime,
+            pad: [0; 4],
         }
     }
 }
@@ -5,6 +5,7 @@ mod pipe;
 mod stdio;
 
 use crate::mm::UserBuffer;
+use easy_fs::Access;
 
 /// trait File for all file types
 pub trait File: Send + Sync {
@@ -20,6 +21,23 @@ pub trait File: Send + Sync {
     fn status(&self) -> FileStatus {
         unimplemented!()
     }
+    /// (uid, gid) owning this file. The default reports root `(0, 0)`; no
+    /// `File` implementation overrides it, so inode-backed files report
+    /// `(0, 0)` too rather than their inode's owner.
+    fn owner(&self) -> (u32, u32) {
+        (0, 0)
+    }
+    /// Check whether a caller identified by `(uid, gid, groups)` may
+    /// perform `want` on this file. The default grants every access; no
+    /// `File` implementation overrides it, so inode-backed files are not
+    /// checked against `easy_fs::Inode::check_access` either.
+    fn check_access(&self, _uid: u32, _gid: u32, _groups: &[u32], _want: Access) -> bool {
+        true
+    }
+    /// Clear this file's setuid/setgid bits. The default does nothing and
+    /// no `File` implementation overrides it, so no file's mode bits are
+    /// changed by this call.
+    fn clear_suid_sgid(&self) {}
 }
 
 pub use inode::{list_apps, open_file, OSInode, OpenFlags};
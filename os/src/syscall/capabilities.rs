@@ -0,0 +1,22 @@
+//! Syscall capability filter syscalls
+
+use crate::task::{restrict_syscalls, CAPABILITY_MASK_BYTES, CAPABILITY_MASK_WORDS};
+use crate::util::UserSlice;
+
+/// Narrow the current task's syscall capability set by intersecting it with
+/// the mask read from `mask_ptr`, a `CAPABILITY_MASK_BYTES`-long buffer of
+/// `CAPABILITY_MASK_WORDS` native-endian `u64`s, one bit per syscall id.
+/// Clearing a bit the task has already lost has no further effect; there is
+/// no way to set a bit back.
+pub fn sys_restrict_syscalls(mask_ptr: *const u8) -> isize {
+    let Ok(slice) = UserSlice::new(mask_ptr, CAPABILITY_MASK_BYTES) else {
+        return -1;
+    };
+    let bytes = slice.copy_out();
+    let mut mask = [0u64; CAPABILITY_MASK_WORDS];
+    for (word, chunk) in mask.iter_mut().zip(bytes.chunks_exact(8)) {
+        *word = u64::from_ne_bytes(chunk.try_into().unwrap());
+    }
+    restrict_syscalls(&mask);
+    0
+}
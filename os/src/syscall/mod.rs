@@ -10,86 +10,118 @@
 //! `sys_` then the name of the syscall. You can find functions like this in
 //! submodules, and you should also implement syscalls this way.
 
-/// unlinkat syscall
-const SYSCALL_UNLINKAT: usize = 35;
-/// linkat syscall
-const SYSCALL_LINKAT: usize = 37;
-/// open syscall
-const SYSCALL_OPEN: usize = 56;
-/// close syscall
-const SYSCALL_CLOSE: usize = 57;
-/// read syscall
-const SYSCALL_READ: usize = 63;
-/// write syscall
-const SYSCALL_WRITE: usize = 64;
-/// fstat syscall
-const SYSCALL_FSTAT: usize = 80;
-/// exit syscall
-const SYSCALL_EXIT: usize = 93;
-/// yield syscall
-const SYSCALL_YIELD: usize = 124;
-/// setpriority syscall
-const SYSCALL_SET_PRIORITY: usize = 140;
-/// gettime syscall
-const SYSCALL_GET_TIME: usize = 169;
-/// getpid syscall
-const SYSCALL_GETPID: usize = 172;
-/// sbrk syscall
-const SYSCALL_SBRK: usize = 214;
-/// munmap syscall
-const SYSCALL_MUNMAP: usize = 215;
-/// fork syscall
-const SYSCALL_FORK: usize = 220;
-/// exec syscall
-const SYSCALL_EXEC: usize = 221;
-/// mmap syscall
-const SYSCALL_MMAP: usize = 222;
-/// waitpid syscall
-const SYSCALL_WAITPID: usize = 260;
-/// spawn syscall
-const SYSCALL_SPAWN: usize = 400;
-/// taskinfo syscall
-const SYSCALL_TASK_INFO: usize = 410;
+/// Declares the syscall dispatch table. Each entry maps a syscall number and
+/// name to its declared argument arity and a `|args: [usize; 4]| -> isize`
+/// handler closure. Expands to the `SYSCALL_*` id constants, `dispatch()`
+/// (the full `match` over every table entry, used to route a validated
+/// `syscall_id` to its handler), and `syscall_name()` (the reverse
+/// id -> (name, arity) lookup `SyscallChecker` uses for diagnostics).
+/// Adding a syscall is a one-line edit to the table in [`syscall()`]; an id
+/// outside the table makes `dispatch()` return `-1` instead of panicking.
+macro_rules! syscall_table {
+    ($(($num:literal, $name:ident, $arity:literal, $handler:expr)),+ $(,)?) => {
+        $(
+            #[allow(non_upper_case_globals)]
+            const $name: usize = $num;
+        )+
+
+        /// Route a `syscall_id` already known to be in the table to its
+        /// handler; returns `-1` for any id the table doesn't list.
+        fn dispatch(syscall_id: usize, args: [usize; 4]) -> isize {
+            match syscall_id {
+                $($name => ($handler)(args),)+
+                _ => -1,
+            }
+        }
 
+        /// The declared name and argument arity of `syscall_id`, for
+        /// `SyscallChecker` diagnostics; `None` for an id not in the table.
+        fn syscall_name(syscall_id: usize) -> Option<(&'static str, usize)> {
+            match syscall_id {
+                $($name => Some((stringify!($name), $arity)),)+
+                _ => None,
+            }
+        }
+    };
+}
+
+mod capabilities;
 mod fs;
 mod process;
+mod seccomp;
 
+use capabilities::*;
 use fs::*;
 use process::*;
+use seccomp::*;
 
 use crate::fs::Stat;
 
+syscall_table! {
+    (35, SYSCALL_UNLINKAT, 1, |args: [usize; 4]| sys_unlinkat(args[1] as *const u8)),
+    (37, SYSCALL_LINKAT, 2, |args: [usize; 4]| sys_linkat(args[1] as *const u8, args[3] as *const u8)),
+    (56, SYSCALL_OPEN, 2, |args: [usize; 4]| sys_open(args[1] as *const u8, args[2] as u32)),
+    (57, SYSCALL_CLOSE, 1, |args: [usize; 4]| sys_close(args[0])),
+    (63, SYSCALL_READ, 3, |args: [usize; 4]| sys_read(args[0], args[1] as *const u8, args[2])),
+    (64, SYSCALL_WRITE, 3, |args: [usize; 4]| sys_write(args[0], args[1] as *const u8, args[2])),
+    (80, SYSCALL_FSTAT, 2, |args: [usize; 4]| sys_fstat(args[0], args[1] as *mut Stat)),
+    (93, SYSCALL_EXIT, 1, |args: [usize; 4]| sys_exit(args[0] as i32)),
+    (103, SYSCALL_SETITIMER, 2, |args: [usize; 4]| sys_setitimer(args[0] as *const ItimerVal, args[1] as *mut ItimerVal)),
+    (124, SYSCALL_YIELD, 0, |_args: [usize; 4]| sys_yield()),
+    (129, SYSCALL_KILL, 2, |args: [usize; 4]| sys_kill(args[0] as isize, args[1] as i32)),
+    (140, SYSCALL_SET_PRIORITY, 1, |args: [usize; 4]| sys_set_priority(args[0] as isize)),
+    (154, SYSCALL_SETPGID, 2, |args: [usize; 4]| sys_setpgid(args[0], args[1])),
+    (155, SYSCALL_GETPGID, 1, |args: [usize; 4]| sys_getpgid(args[0])),
+    (157, SYSCALL_SETSID, 0, |_args: [usize; 4]| sys_setsid()),
+    (165, SYSCALL_GETRUSAGE, 2, |args: [usize; 4]| sys_getrusage(args[0] as i32, args[1] as *mut Rusage)),
+    (169, SYSCALL_GET_TIME, 2, |args: [usize; 4]| sys_get_time(args[0] as *mut TimeVal, args[1])),
+    (172, SYSCALL_GETPID, 0, |_args: [usize; 4]| sys_getpid()),
+    (214, SYSCALL_SBRK, 1, |args: [usize; 4]| sys_sbrk(args[0] as i32)),
+    (215, SYSCALL_MUNMAP, 2, |args: [usize; 4]| sys_munmap(args[0], args[1])),
+    (220, SYSCALL_FORK, 0, |_args: [usize; 4]| sys_fork()),
+    (221, SYSCALL_EXEC, 1, |args: [usize; 4]| sys_exec(args[0] as *const u8)),
+    (222, SYSCALL_MMAP, 3, |args: [usize; 4]| sys_mmap(args[0], args[1], args[2])),
+    (260, SYSCALL_WAITPID, 2, |args: [usize; 4]| sys_waitpid(args[0] as isize, args[1] as *mut i32)),
+    (261, SYSCALL_PRLIMIT, 4, |args: [usize; 4]| sys_prlimit(args[0], args[1], args[2] as *const Rlimit, args[3] as *mut Rlimit)),
+    (400, SYSCALL_SPAWN, 1, |args: [usize; 4]| sys_spawn(args[0] as *const u8)),
+    (401, SYSCALL_RESTRICT_SYSCALLS, 1, |args: [usize; 4]| sys_restrict_syscalls(args[0] as *const u8)),
+    (402, SYSCALL_ALARM, 1, |args: [usize; 4]| sys_alarm(args[0])),
+    (403, SYSCALL_TRACE, 1, |args: [usize; 4]| sys_syscall_trace(args[0] as *mut SyscallTrace)),
+    (410, SYSCALL_TASK_INFO, 1, |args: [usize; 4]| sys_task_info(args[0] as *mut TaskInfo)),
+    (500, SYSCALL_SECCOMP, 3, |args: [usize; 4]| sys_seccomp(args[0], args[1], args[2] as isize)),
+}
+
 /// handle syscall exception with `syscall_id` and other arguments
 pub fn syscall(syscall_id: usize, args: [usize; 4]) -> isize {
     let _guard = SyscallChecker::new(syscall_id, args);
 
     update_syscall_times(syscall_id);
-    match syscall_id {
-        SYSCALL_OPEN => sys_open(args[1] as *const u8, args[2] as u32),
-        SYSCALL_CLOSE => sys_close(args[0]),
-        SYSCALL_LINKAT => sys_linkat(args[1] as *const u8, args[3] as *const u8),
-        SYSCALL_UNLINKAT => sys_unlinkat(args[1] as *const u8),
-        SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
-        SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
-        SYSCALL_FSTAT => sys_fstat(args[0], args[1] as *mut Stat),
-        SYSCALL_EXIT => sys_exit(args[0] as i32),
-        SYSCALL_YIELD => sys_yield(),
-        SYSCALL_GETPID => sys_getpid(),
-        SYSCALL_FORK => sys_fork(),
-        SYSCALL_EXEC => sys_exec(args[0] as *const u8),
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
-        SYSCALL_GET_TIME => sys_get_time(args[0] as *mut TimeVal, args[1]),
-        SYSCALL_TASK_INFO => sys_task_info(args[0] as *mut TaskInfo),
-        SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2]),
-        SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
-        SYSCALL_SBRK => sys_sbrk(args[0] as i32),
-        SYSCALL_SPAWN => sys_spawn(args[0] as *const u8),
-        SYSCALL_SET_PRIORITY => sys_set_priority(args[0] as isize),
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+    if !syscall_allowed(syscall_id) {
+        return -1;
     }
+    match seccomp_action(syscall_id) {
+        SeccompAction::Allow => {}
+        SeccompAction::Errno(value) => return value,
+        SeccompAction::Kill => {
+            exit_current_and_run_next(-1);
+            return 0; // the task above has exited and never resumes here
+        }
+        SeccompAction::Trap => {
+            raise_current_signal(SignalFlags::SIGSYS);
+            return -1;
+        }
+    }
+    dispatch(syscall_id, args)
 }
 
-use crate::{sync::UPSafeCell, task::update_syscall_times};
+use crate::{
+    sync::UPSafeCell,
+    task::{
+        exit_current_and_run_next, raise_current_signal, record_syscall_trace, seccomp_action,
+        syscall_allowed, update_syscall_times, Rlimit, SeccompAction, SignalFlags, SyscallTrace,
+    },
+    timer::get_time_us,
+};
 
 /// Checker state in syscall
 struct SyscallCheckerState {
@@ -109,17 +141,25 @@ impl SyscallCheckerState {
 static CHECKER_STATE: UPSafeCell<SyscallCheckerState> =
     unsafe { UPSafeCell::const_new(SyscallCheckerState::new()) };
 
-/// Syscall checker
+/// Syscall checker, also the single point where per-task syscall tracing is
+/// recorded: its `Drop` runs whenever [`syscall()`] returns. A syscall that
+/// never returns to its own call site (like [`SYSCALL_EXIT`]) never drops
+/// its checker, so no trace is recorded for it.
 #[allow(dead_code)]
 #[derive(Debug)]
-struct SyscallChecker<Args> {
+struct SyscallChecker<Args: Into<[usize; 4]> + Copy> {
     syscall_id: usize,
     args: Args,
+    start_us: usize,
 }
 
-impl<Args> SyscallChecker<Args> {
+impl<Args: Into<[usize; 4]> + Copy> SyscallChecker<Args> {
     fn new(syscall_id: usize, args: Args) -> SyscallChecker<Args> {
-        let checker = SyscallChecker { syscall_id, args };
+        let checker = SyscallChecker {
+            syscall_id,
+            args,
+            start_us: get_time_us(),
+        };
         checker.start();
         checker
     }
@@ -154,10 +194,20 @@ impl<Args> SyscallChecker<Args> {
             "counter: {:?}",
             state.counter
         );
+        drop(state);
+
+        let elapsed_us = (get_time_us() - self.start_us) as u64;
+        let mut args: [usize; 4] = self.args.into();
+        // Blank out the args this syscall never declared, so a read-back
+        // trace doesn't show stale or out-of-bounds-looking values.
+        if let Some((_, arity)) = syscall_name(self.syscall_id) {
+            args[arity..].fill(0);
+        }
+        record_syscall_trace(self.syscall_id, args, elapsed_us);
     }
 }
 
-impl<Args> Drop for SyscallChecker<Args> {
+impl<Args: Into<[usize; 4]> + Copy> Drop for SyscallChecker<Args> {
     fn drop(&mut self) {
         self.finalize();
     }
@@ -5,24 +5,66 @@ use crate::{
     fs::{open_file, OpenFlags},
     mm::{translated_ref, translated_refmut, translated_str},
     task::{
-        add_task, current_task, current_user_token, exit_current_and_run_next, pid2task,
-        suspend_current_and_run_next, SignalAction, SignalFlags, TaskStatus, MAX_SIG,
+        add_task, all_tasks, current_task, current_user_token, exit_current_and_run_next,
+        pid2task, suspend_current_and_run_next, Resource, Rlimit, SignalAction, SignalFlags,
+        TaskControlBlock, TaskStatus, MAX_SIG,
     },
 };
 use alloc::{string::String, sync::Arc, vec::Vec};
 
 use crate::config::PAGE_SIZE;
-use crate::task::{current_task_info, mmap, munmap, Priority};
+use crate::task::{current_task_info, mmap, munmap, syscall_trace, Priority, SyscallTrace};
 use crate::timer::{get_time_us, MICRO_PER_SEC, MSEC_PER_SEC};
 use crate::util::UserSpacePtr;
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct TimeVal {
     pub sec: usize,
     pub usec: usize,
 }
 
+impl TimeVal {
+    fn from_us(us: usize) -> Self {
+        Self {
+            sec: us / MICRO_PER_SEC,
+            usec: us % MICRO_PER_SEC,
+        }
+    }
+}
+
+/// Report current CPU usage of either the calling task (`RUSAGE_SELF`) or
+/// its reaped children (`RUSAGE_CHILDREN`), mirroring `getrusage(2)`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rusage {
+    /// Time spent executing user-space instructions
+    pub utime: TimeVal,
+    /// Time spent executing on the task's behalf in the kernel
+    pub stime: TimeVal,
+}
+
+/// Report usage for the calling task itself
+pub const RUSAGE_SELF: i32 = 0;
+/// Report aggregated usage of reaped children
+pub const RUSAGE_CHILDREN: i32 = -1;
+
+/// Userspace view of a task's `ITIMER_REAL` timer, mirroring `itimerval`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ItimerVal {
+    /// Interval between successive firings (`0` for a one-shot timer)
+    pub it_interval: TimeVal,
+    /// Time until the next firing
+    pub it_value: TimeVal,
+}
+
+impl TimeVal {
+    fn to_us(self) -> usize {
+        self.sec * MICRO_PER_SEC + self.usec
+    }
+}
+
 /// Task information
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -55,6 +97,9 @@ pub fn sys_getpid() -> isize {
 pub fn sys_fork() -> isize {
     trace!("kernel:pid[{}] sys_fork", current_task().unwrap().pid.0);
     let current_task = current_task().unwrap();
+    if !nproc_within_limit(&current_task) {
+        return -1;
+    }
     let new_task = current_task.fork();
     let new_pid = new_task.pid.0;
     // modify trap context of new_task, because it returns immediately after switching
@@ -62,7 +107,8 @@ pub fn sys_fork() -> isize {
     // we do not have to move to next instruction since we have done it before
     // for child process, fork returns 0
     trap_cx.x[10] = 0;
-    // add new task to scheduler
+    // enqueue the child; `run_tasks` picks it up through `fetch_task` the
+    // same way it does every other `Ready` task
     add_task(new_task);
     new_pid as isize
 }
@@ -127,6 +173,9 @@ pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
         let found_pid = child.getpid();
         // ++++ temporarily access child PCB exclusively
         let exit_code = child.inner_exclusive_access().exit_code;
+        inner
+            .children_running_times
+            .accumulate(&child.inner_exclusive_access().infos.running_times);
         // ++++ release child PCB
         *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
         found_pid as isize
@@ -136,16 +185,47 @@ pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
     // ---- release current PCB automatically
 }
 
-pub fn sys_kill(pid: usize, signum: i32) -> isize {
+/// Whether spawning one more task owned by `task` would keep the number of
+/// live tasks within its `RLIMIT_NPROC` soft limit.
+fn nproc_within_limit(task: &Arc<TaskControlBlock>) -> bool {
+    let limit = task
+        .inner_exclusive_access()
+        .rlimits
+        .get(Resource::Nproc)
+        .soft;
+    all_tasks().len() < limit
+}
+
+/// Insert `flag` into `task`'s pending signals, unless it is already set.
+/// Returns whether the signal was newly delivered.
+fn deliver_signal(task: &Arc<TaskControlBlock>, flag: SignalFlags) -> bool {
+    let mut inner = task.inner_exclusive_access();
+    if inner.signals.contains(flag) {
+        return false;
+    }
+    inner.signals.insert(flag);
+    true
+}
+
+/// A negative `pid` broadcasts the signal to every task whose `pgid`
+/// matches `-pid`, the same convention `kill(2)` uses for process groups.
+pub fn sys_kill(pid: isize, signum: i32) -> isize {
     trace!("kernel:pid[{}] sys_kill", current_task().unwrap().pid.0);
-    if let Some(task) = pid2task(pid) {
-        if let Some(flag) = SignalFlags::from_bits(1 << signum) {
-            // insert the signal if legal
-            let mut task_ref = task.inner_exclusive_access();
-            if task_ref.signals.contains(flag) {
-                return -1;
+    let Some(flag) = SignalFlags::from_bits(1 << signum) else {
+        return -1;
+    };
+    if pid < 0 {
+        let pgid = (-pid) as usize;
+        let mut delivered = false;
+        for task in all_tasks() {
+            if task.inner_exclusive_access().pgid == pgid {
+                delivered |= deliver_signal(&task, flag);
             }
-            task_ref.signals.insert(flag);
+        }
+        return if delivered { 0 } else { -1 };
+    }
+    if let Some(task) = pid2task(pid as usize) {
+        if deliver_signal(&task, flag) {
             0
         } else {
             -1
@@ -155,10 +235,64 @@ pub fn sys_kill(pid: usize, signum: i32) -> isize {
     }
 }
 
+/// Get the process group id of the current task, or of `pid` if nonzero.
+pub fn sys_getpgid(pid: usize) -> isize {
+    trace!("kernel:pid[{}] sys_getpgid(pid: {pid})", current_task().unwrap().pid.0);
+    let task = if pid == 0 {
+        current_task().unwrap()
+    } else {
+        match pid2task(pid) {
+            Some(task) => task,
+            None => return -1,
+        }
+    };
+    task.inner_exclusive_access().pgid as isize
+}
+
+/// Move `pid` (the current task if `0`) into process group `pgid`
+/// (its own pid if `0`). `pgid` is inherited by `fork`/`spawn` children.
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    trace!(
+        "kernel:pid[{}] sys_setpgid(pid: {pid}, pgid: {pgid})",
+        current_task().unwrap().pid.0
+    );
+    let task = if pid == 0 {
+        current_task().unwrap()
+    } else {
+        match pid2task(pid) {
+            Some(task) => task,
+            None => return -1,
+        }
+    };
+    let new_pgid = if pgid == 0 { task.getpid() } else { pgid };
+    task.inner_exclusive_access().pgid = new_pgid;
+    0
+}
+
+/// Start a new session with the current task as its leader: its `sid` and
+/// `pgid` both become its own pid. Returns the new session id.
+pub fn sys_setsid() -> isize {
+    trace!("kernel:pid[{}] sys_setsid", current_task().unwrap().pid.0);
+    let task = current_task().unwrap();
+    let pid = task.getpid();
+    let mut inner = task.inner_exclusive_access();
+    inner.sid = pid;
+    inner.pgid = pid;
+    pid as isize
+}
+
 /// change data segment size
 pub fn sys_sbrk(size: i32) -> isize {
     trace!("kernel:pid[{}] sys_sbrk", current_task().unwrap().pid.0);
-    if let Some(old_brk) = current_task().unwrap().change_program_brk(size) {
+    let task = current_task().unwrap();
+    if size > 0 {
+        let inner = task.inner_exclusive_access();
+        let grown = inner.program_brk + size as usize - inner.heap_bottom;
+        if grown > inner.rlimits.get(Resource::Data).soft {
+            return -1;
+        }
+    }
+    if let Some(old_brk) = task.change_program_brk(size) {
         old_brk as isize
     } else {
         -1
@@ -253,11 +387,14 @@ pub fn sys_get_time(ts: *mut TimeVal, _tz: usize) -> isize {
         current_task().unwrap().pid.0
     );
     let now_us = get_time_us();
-    unsafe {
+    let result = unsafe {
         UserSpacePtr::from(ts).write(TimeVal {
             sec: now_us / MICRO_PER_SEC,
             usec: now_us % MICRO_PER_SEC,
-        });
+        })
+    };
+    if result.is_err() {
+        return -1;
     }
     0
 }
@@ -283,16 +420,106 @@ pub fn sys_task_info(ti: *mut TaskInfo) -> isize {
         let elapsed = now_us - info.running_times.first_run_time_us;
         elapsed / (MICRO_PER_SEC / MSEC_PER_SEC)
     };
-    unsafe {
+    let result = unsafe {
         UserSpacePtr::from(ti).write(TaskInfo {
             status,
             syscall_times,
             time: time_ms,
-        });
+        })
+    };
+    if result.is_err() {
+        return -1;
+    }
+    0
+}
+
+/// Copy the current task's syscall trace (per-syscall-id invocation count,
+/// cumulative time, and most recent arguments) out to `trace`.
+pub fn sys_syscall_trace(trace: *mut SyscallTrace) -> isize {
+    trace!(
+        "kernel:pid[{}] sys_syscall_trace(trace: 0x{trace:X?})",
+        current_task().unwrap().pid.0
+    );
+    let result = unsafe { UserSpacePtr::from(trace).write(syscall_trace()) };
+    if result.is_err() {
+        return -1;
     }
     0
 }
 
+/// Report CPU usage via a `Rusage`-like struct. `who` is `RUSAGE_SELF` for
+/// the calling task's own accumulated time or `RUSAGE_CHILDREN` for the
+/// aggregated time of every child it has already reaped through
+/// `sys_waitpid`.
+pub fn sys_getrusage(who: i32, usage: *mut Rusage) -> isize {
+    trace!(
+        "kernel:pid[{}] sys_getrusage(who: {who})",
+        current_task().unwrap().pid.0
+    );
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let running_times = match who {
+        RUSAGE_SELF => inner.infos.running_times,
+        RUSAGE_CHILDREN => inner.children_running_times,
+        _ => return -1,
+    };
+    drop(inner);
+    let result = unsafe {
+        UserSpacePtr::from(usage).write(Rusage {
+            utime: TimeVal::from_us(running_times.user_time_us),
+            stime: TimeVal::from_us(running_times.kernel_time_us),
+        })
+    };
+    if result.is_err() {
+        return -1;
+    }
+    0
+}
+
+/// Install a new `ITIMER_REAL` timer for the current task, writing the
+/// previously configured timer back through `old` (either pointer may be
+/// null to skip that half of the exchange).
+pub fn sys_setitimer(value: *const ItimerVal, old: *mut ItimerVal) -> isize {
+    trace!(
+        "kernel:pid[{}] sys_setitimer",
+        current_task().unwrap().pid.0
+    );
+    let token = current_user_token();
+    let now_us = get_time_us();
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+
+    if !old.is_null() {
+        *translated_refmut(token, old) = ItimerVal {
+            it_interval: TimeVal::from_us(inner.timer.interval_us()),
+            it_value: TimeVal::from_us(inner.timer.remaining_us(now_us)),
+        };
+    }
+    if !value.is_null() {
+        let new_value = *translated_ref(token, value);
+        inner
+            .timer
+            .set(now_us, new_value.it_value.to_us(), new_value.it_interval.to_us());
+    }
+    0
+}
+
+/// Arm a one-shot `ITIMER_REAL` timer to deliver `SIGALRM` in `seconds`
+/// seconds (`0` disarms it), returning the number of seconds left on any
+/// timer that was previously armed.
+pub fn sys_alarm(seconds: usize) -> isize {
+    trace!(
+        "kernel:pid[{}] sys_alarm(seconds: {seconds})",
+        current_task().unwrap().pid.0
+    );
+    let now_us = get_time_us();
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let previous_remaining_sec = inner.timer.remaining_us(now_us).div_ceil(MICRO_PER_SEC);
+    inner.timer.set(now_us, seconds * MICRO_PER_SEC, 0);
+    previous_remaining_sec as isize
+}
+
 /// mmap
 pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> isize {
     trace!(
@@ -305,10 +532,24 @@ pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> isize {
     let valid_prot = (prot & !PROT_MASK) == 0;
     let prot_none = (prot & PROT_MASK) == 0;
 
-    if addr_aligned && valid_prot && !prot_none {
-        return mmap(addr, len, prot);
+    if !(addr_aligned && valid_prot && !prot_none) {
+        return -1;
+    }
+
+    let len_pages = len.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.mapped_bytes + len_pages > inner.rlimits.get(Resource::AddressSpace).soft {
+        return -1;
+    }
+    drop(inner);
+
+    let result = mmap(addr, len, prot);
+    if result == 0 {
+        task.inner_exclusive_access().mapped_bytes += len_pages;
     }
-    -1
+    result
 }
 
 /// munmap
@@ -320,10 +561,18 @@ pub fn sys_munmap(addr: usize, len: usize) -> isize {
 
     let addr_aligned = addr % PAGE_SIZE == 0;
 
-    if addr_aligned {
-        return munmap(addr, len);
+    if !addr_aligned {
+        return -1;
+    }
+
+    let result = munmap(addr, len);
+    if result == 0 {
+        let len_pages = len.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+        let task = current_task().unwrap();
+        let mut inner = task.inner_exclusive_access();
+        inner.mapped_bytes = inner.mapped_bytes.saturating_sub(len_pages);
     }
-    -1
+    result
 }
 
 /// spawn
@@ -335,10 +584,17 @@ pub fn sys_spawn(path: *const u8) -> isize {
         current_task().unwrap().pid.0
     );
 
+    let current_task = current_task().unwrap();
+    if !nproc_within_limit(&current_task) {
+        return -1;
+    }
+
     if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
         let elf_data = app_inode.read_all();
-        let new_task = current_task().unwrap().spawn(&elf_data);
+        let new_task = current_task.spawn(&elf_data);
         let new_pid = new_task.getpid();
+        // enqueue the child; `run_tasks` picks it up through `fetch_task` the
+        // same way it does every other `Ready` task
         add_task(new_task);
         new_pid as isize
     } else {
@@ -346,6 +602,49 @@ pub fn sys_spawn(path: *const u8) -> isize {
     }
 }
 
+/// Whether `task` (pid 0, the initial process) may raise a hard resource
+/// limit. This kernel has no user/capability model yet, so pid 0 is
+/// treated as the privileged one, matching its role as the root of the
+/// process tree.
+fn is_privileged(task: &Arc<TaskControlBlock>) -> bool {
+    task.getpid() == 0
+}
+
+/// Query and/or set `pid`'s (the current task's if `0`) limit for
+/// `resource`, mirroring `prlimit64(2)`. Either `new` or `old` may be null
+/// to skip that half of the exchange. Setting clamps the soft limit to the
+/// hard limit and rejects a non-privileged attempt to raise the hard limit.
+pub fn sys_prlimit(pid: usize, resource: usize, new: *const Rlimit, old: *mut Rlimit) -> isize {
+    trace!(
+        "kernel:pid[{}] sys_prlimit(pid: {pid}, resource: {resource})",
+        current_task().unwrap().pid.0
+    );
+    let Some(resource) = Resource::from_id(resource) else {
+        return -1;
+    };
+    let task = if pid == 0 {
+        current_task().unwrap()
+    } else {
+        match pid2task(pid) {
+            Some(task) => task,
+            None => return -1,
+        }
+    };
+    let token = current_user_token();
+    let privileged = is_privileged(&current_task().unwrap());
+    let mut inner = task.inner_exclusive_access();
+    if !old.is_null() {
+        *translated_refmut(token, old) = inner.rlimits.get(resource);
+    }
+    if !new.is_null() {
+        let new_limit = *translated_ref(token, new);
+        if !inner.rlimits.set(resource, new_limit, privileged) {
+            return -1;
+        }
+    }
+    0
+}
+
 // Set task priority
 pub fn sys_set_priority(pri: isize) -> isize {
     trace!(
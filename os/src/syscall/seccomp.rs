@@ -0,0 +1,38 @@
+//! Seccomp-related syscalls
+
+use crate::task::{lock_seccomp, set_seccomp_action, SeccompAction};
+
+/// Install a rule mapping `syscall_id` to `action`.
+pub const SECCOMP_SET_FILTER: usize = 0;
+/// Lock the filter (`no_new_privs`); further calls may only tighten
+/// existing rules.
+pub const SECCOMP_LOCK: usize = 1;
+
+/// Decode the raw `action` argument of `sys_seccomp`. Non-negative values
+/// name a fixed action; any negative value is an `Errno` to hand back to
+/// the caller in place of running the syscall.
+fn decode_action(action: isize) -> Option<SeccompAction> {
+    match action {
+        0 => Some(SeccompAction::Allow),
+        1 => Some(SeccompAction::Kill),
+        2 => Some(SeccompAction::Trap),
+        errno if errno < 0 => Some(SeccompAction::Errno(errno)),
+        _ => None,
+    }
+}
+
+/// Configure the current task's seccomp filter.
+pub fn sys_seccomp(mode: usize, syscall_id: usize, action: isize) -> isize {
+    trace!("kernel: sys_seccomp(mode: {mode}, syscall_id: {syscall_id}, action: {action})");
+    match mode {
+        SECCOMP_LOCK => {
+            lock_seccomp();
+            0
+        }
+        SECCOMP_SET_FILTER => match decode_action(action) {
+            Some(action) if set_seccomp_action(syscall_id, action) => 0,
+            _ => -1,
+        },
+        _ => -1,
+    }
+}
@@ -0,0 +1,59 @@
+//! Per-task syscall capability filter
+//!
+//! Where [`super::seccomp::SeccompFilter`] lets a task script a *response*
+//! per syscall (allow / return an errno / trap / kill), `SyscallCapabilities`
+//! is a coarser, tightening-only allowlist: a syscall either is or isn't
+//! granted to this task at all, checked in [`crate::syscall::syscall`]
+//! before dispatch and before the seccomp filter even runs. Following the
+//! capability model of microkernels like Tock, a task can only narrow its
+//! own set (and that of any `fork`/`spawn` child, which inherits it by
+//! value) — never widen it back out.
+
+use core::mem::size_of;
+
+use crate::config::MAX_SYSCALL_NUM;
+
+/// Number of `u64` words needed to hold one bit per syscall id up to
+/// `MAX_SYSCALL_NUM`.
+pub const CAPABILITY_MASK_WORDS: usize = MAX_SYSCALL_NUM.div_ceil(u64::BITS as usize);
+
+/// The byte length of the mask buffer `sys_restrict_syscalls` reads out of
+/// user space.
+pub const CAPABILITY_MASK_BYTES: usize = CAPABILITY_MASK_WORDS * size_of::<u64>();
+
+/// A task's syscall capability set: one bit per syscall id, set meaning
+/// granted. Starts fully granted; [`Self::restrict`] can only clear bits,
+/// never set them, so a task can sandbox itself but never escalate.
+#[derive(Debug, Clone, Copy)]
+pub struct SyscallCapabilities {
+    granted: [u64; CAPABILITY_MASK_WORDS],
+}
+
+impl SyscallCapabilities {
+    /// Every syscall granted.
+    pub const fn new() -> Self {
+        Self { granted: [u64::MAX; CAPABILITY_MASK_WORDS] }
+    }
+
+    /// Whether `syscall_id` is currently granted to this task.
+    pub fn is_allowed(&self, syscall_id: usize) -> bool {
+        let word = syscall_id / u64::BITS as usize;
+        let bit = syscall_id % u64::BITS as usize;
+        word < CAPABILITY_MASK_WORDS && self.granted[word] & (1 << bit) != 0
+    }
+
+    /// Intersect this capability set with `mask` (same one-bit-per-syscall
+    /// layout as [`Self::is_allowed`]), clearing any bit `mask` doesn't also
+    /// grant. A bit already cleared stays cleared regardless of `mask`.
+    pub fn restrict(&mut self, mask: &[u64; CAPABILITY_MASK_WORDS]) {
+        for (word, &bits) in self.granted.iter_mut().zip(mask.iter()) {
+            *word &= bits;
+        }
+    }
+}
+
+impl Default for SyscallCapabilities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
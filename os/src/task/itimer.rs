@@ -0,0 +1,57 @@
+//! `ITIMER_REAL` interval timer state
+
+/// A task's `ITIMER_REAL` timer: fires once `value_us` from when it was
+/// armed, then every `interval_us` after that (or not again, if
+/// `interval_us` is zero). `next_expire_us == 0` means disarmed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ItimerState {
+    next_expire_us: usize,
+    interval_us: usize,
+}
+
+impl ItimerState {
+    /// A disarmed timer.
+    pub const fn disarmed() -> Self {
+        Self {
+            next_expire_us: 0,
+            interval_us: 0,
+        }
+    }
+
+    /// Arm the timer to first fire `value_us` from `now_us`, repeating every
+    /// `interval_us` after that. `value_us == 0` disarms the timer instead.
+    pub fn set(&mut self, now_us: usize, value_us: usize, interval_us: usize) {
+        self.next_expire_us = if value_us == 0 { 0 } else { now_us + value_us };
+        self.interval_us = interval_us;
+    }
+
+    /// The configured repeat interval, in microseconds (`0` for a one-shot
+    /// timer).
+    pub fn interval_us(&self) -> usize {
+        self.interval_us
+    }
+
+    /// Microseconds left until the next expiration, or `0` if disarmed or
+    /// already due.
+    pub fn remaining_us(&self, now_us: usize) -> usize {
+        if self.next_expire_us == 0 {
+            0
+        } else {
+            self.next_expire_us.saturating_sub(now_us)
+        }
+    }
+
+    /// If the timer has expired as of `now_us`, rearm it (periodic) or
+    /// disarm it (one-shot) and return `true`.
+    pub fn check_expired(&mut self, now_us: usize) -> bool {
+        if self.next_expire_us == 0 || now_us < self.next_expire_us {
+            return false;
+        }
+        self.next_expire_us = if self.interval_us == 0 {
+            0
+        } else {
+            now_us + self.interval_us
+        };
+        true
+    }
+}
@@ -0,0 +1,51 @@
+//! Kernel stack allocation, indexed by pid
+
+use super::pid::PidHandle;
+use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE, TRAMPOLINE};
+use crate::mm::{MapPermission, VirtAddr, KERNEL_SPACE};
+
+/// The `[bottom, top)` virtual address range of the kernel stack belonging
+/// to the task with the given `pid`, guard-paged from its neighbours by the
+/// unmapped `PAGE_SIZE` gap above `top`.
+fn kernel_stack_position(pid: usize) -> (usize, usize) {
+    let top = TRAMPOLINE - pid * (KERNEL_STACK_SIZE + PAGE_SIZE);
+    let bottom = top - KERNEL_STACK_SIZE;
+    (bottom, top)
+}
+
+/// A task's kernel-mode stack, mapped into kernel space at a slot indexed
+/// by its pid and torn down when the task (and its `PidHandle`) is dropped.
+pub struct KernelStack {
+    pid: usize,
+}
+
+impl KernelStack {
+    /// Map a fresh kernel stack for the task owning `pid_handle`.
+    pub fn new(pid_handle: &PidHandle) -> Self {
+        let pid = pid_handle.0;
+        let (bottom, top) = kernel_stack_position(pid);
+        KERNEL_SPACE.exclusive_access().insert_framed_area(
+            VirtAddr::from(bottom),
+            VirtAddr::from(top),
+            MapPermission::R | MapPermission::W,
+        );
+        Self { pid }
+    }
+
+    /// The address one past the top of this stack, i.e. the initial stack
+    /// pointer for a task switching into it.
+    pub fn top(&self) -> usize {
+        let (_, top) = kernel_stack_position(self.pid);
+        top
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        let (bottom, _) = kernel_stack_position(self.pid);
+        let bottom_vpn = VirtAddr::from(bottom).into();
+        KERNEL_SPACE
+            .exclusive_access()
+            .remove_area_with_start_vpn(bottom_vpn);
+    }
+}
@@ -0,0 +1,94 @@
+//! Implementation of [`TaskManager`]
+//!
+//! It is only a ready queue of tasks: it has nothing to do with the actual
+//! switching of tasks, which is the responsibility of [`super::processor`].
+
+use super::priority::Priority;
+use super::scheduler::{ActivePolicy, Candidate, SchedulerPolicy};
+use super::TaskControlBlock;
+use crate::sync::UPSafeCell;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// A ready queue of tasks waiting to be scheduled onto a `Processor`, ordered
+/// by whichever `SchedulerPolicy` is active.
+pub struct TaskManager {
+    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    policy: ActivePolicy,
+}
+
+impl TaskManager {
+    /// Create an empty `TaskManager`
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+            policy: ActivePolicy::default(),
+        }
+    }
+
+    /// Push `task` onto the ready queue, letting the active policy record
+    /// its arrival.
+    pub fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let mut inner = task.inner_exclusive_access();
+        self.policy.on_enqueue(&mut inner.scheduler);
+        drop(inner);
+        self.ready_queue.push_back(task);
+    }
+
+    /// Ask the active policy which `Ready` task should run next among every
+    /// task currently in the queue, remove it, and charge it for the time
+    /// slice it is about to run.
+    pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let candidates: Vec<Candidate> = self
+            .ready_queue
+            .iter()
+            .enumerate()
+            .map(|(id, task)| {
+                let task_inner = task.inner_exclusive_access();
+                Candidate {
+                    id,
+                    state: task_inner.scheduler,
+                    priority: task_inner.priority,
+                }
+            })
+            .collect();
+        let idx = self.policy.pick_next(&candidates)?;
+        let task = self.ready_queue.remove(idx)?;
+
+        let mut inner = task.inner_exclusive_access();
+        let priority = inner.priority;
+        self.policy.on_tick(&mut inner.scheduler, priority);
+        drop(inner);
+
+        Some(task)
+    }
+
+    /// Map a requested nice-like value into the active policy's valid
+    /// priority range.
+    pub fn normalize_priority(&self, requested: isize) -> Priority {
+        self.policy.normalize_priority(requested)
+    }
+}
+
+lazy_static! {
+    static ref TASK_MANAGER: UPSafeCell<TaskManager> = unsafe { UPSafeCell::new(TaskManager::new()) };
+}
+
+/// Add `task` to the ready queue
+pub fn add_task(task: Arc<TaskControlBlock>) {
+    TASK_MANAGER.exclusive_access().add(task);
+}
+
+/// Ask the active `SchedulerPolicy` for the next task to run, removing it
+/// from the ready queue
+pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
+    TASK_MANAGER.exclusive_access().fetch()
+}
+
+/// Map a requested nice-like value into the active policy's valid priority
+/// range
+pub fn normalize_priority(requested: isize) -> Priority {
+    TASK_MANAGER.exclusive_access().normalize_priority(requested)
+}
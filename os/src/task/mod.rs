@@ -3,378 +3,263 @@
 //! Everything about task management, like starting and switching tasks is
 //! implemented here.
 //!
-//! A single global instance of [`TaskManager`] called `TASK_MANAGER` controls
-//! all the tasks in the operating system.
+//! Tasks live as `Arc<TaskControlBlock>`s, queued onto [`manager::TaskManager`]
+//! (a ready queue, `manager::add_task`/`manager::fetch_task`) and run one at a
+//! time on the single [`processor::Processor`] (`processor::PROCESSOR`),
+//! which tracks whichever task is currently executing.
 //!
 //! Be careful when you see `__switch` ASM function in `switch.S`. Control flow around this function
 //! might not be what you expect.
 
+mod capabilities;
 mod context;
+mod itimer;
+mod kernel_stack;
+mod manager;
+mod pid;
+mod priority;
+mod processor;
+mod rlimit;
+mod scheduler;
+mod seccomp;
+mod signal;
+mod stride;
 mod switch;
 #[allow(clippy::module_inception)]
 mod task;
+mod trace;
 
-use crate::loader::{get_app_data, get_num_app};
-use crate::sync::UPSafeCell;
+use crate::config::PAGE_SIZE;
+use crate::loader::get_app_data;
+use crate::mm::{MapPermission, VirtAddr, VirtPageNum};
 use crate::timer::get_time_us;
-use crate::trap::TrapContext;
-use alloc::vec::Vec;
+use alloc::sync::Arc;
 use lazy_static::*;
-use switch::__switch;
+pub use capabilities::{CAPABILITY_MASK_BYTES, CAPABILITY_MASK_WORDS};
+pub use itimer::ItimerState;
+pub use manager::{add_task, fetch_task};
+pub use pid::{all_tasks, insert_into_pid2task, pid2task, pid_alloc, remove_from_pid2task, PidHandle};
+pub use priority::Priority;
+pub use processor::{
+    current_task, current_trap_cx, current_user_token, kernel_timer_start, kernel_timer_stop,
+    run_tasks, schedule, take_current_task, user_timer_start, user_timer_stop,
+};
+pub use rlimit::{Resource, ResourceLimits, Rlimit};
+pub use seccomp::SeccompAction;
+pub use signal::{SignalAction, SignalFlags, MAX_SIG};
 use task::TaskInfoBlock;
+pub use trace::{SyscallStats, SyscallTrace};
 pub use task::{TaskControlBlock, TaskStatus};
 
 pub use context::TaskContext;
 
-/// The task manager, where all the tasks are managed.
-///
-/// Functions implemented on `TaskManager` deals with all task state transitions
-/// and task context switching. For convenience, you can find wrappers around it
-/// in the module level.
-///
-/// Most of `TaskManager` are hidden behind the field `inner`, to defer
-/// borrowing checks to runtime. You can see examples on how to use `inner` in
-/// existing functions on `TaskManager`.
-pub struct TaskManager {
-    /// total number of tasks
-    num_app: usize,
-    /// use inner value to get mutable access
-    inner: UPSafeCell<TaskManagerInner>,
-}
-
-/// The task manager inner in 'UPSafeCell'
-struct TaskManagerInner {
-    /// task list
-    tasks: Vec<TaskControlBlock>,
-    /// id of current `Running` task
-    current_task: usize,
-    /// user timer
-    user_timer_us: usize,
-    /// kernel timer
-    kernel_timer_us: usize,
-}
-
 lazy_static! {
-    /// a `TaskManager` global instance through lazy_static!
-    pub static ref TASK_MANAGER: TaskManager = {
-        println!("init TASK_MANAGER");
-        let num_app = get_num_app();
-        println!("num_app = {}", num_app);
-        let mut tasks: Vec<TaskControlBlock> = Vec::new();
-        for i in 0..num_app {
-            tasks.push(TaskControlBlock::new(get_app_data(i), i));
-        }
-        TaskManager {
-            num_app,
-            inner: unsafe {
-                UPSafeCell::new(TaskManagerInner {
-                    tasks,
-                    current_task: 0,
-                    user_timer_us: 0,
-                    kernel_timer_us: 0,
-                })
-            },
-        }
+    /// The root of the dynamic process tree. Every task whose parent exits
+    /// while it is still alive is reparented here, the way pid 1 adopts
+    /// orphans on a real Unix system.
+    pub static ref INITPROC: Arc<TaskControlBlock> = {
+        let initproc = Arc::new(TaskControlBlock::new(get_app_data(0), 0));
+        insert_into_pid2task(initproc.getpid(), Arc::clone(&initproc));
+        initproc
     };
 }
 
-impl TaskManager {
-    /// Run the first task in task list.
-    ///
-    /// Generally, the first task in task list is an idle task (we call it zero process later).
-    /// But in ch4, we load apps statically, so the first task is a real app.
-    fn run_first_task(&self) -> ! {
-        let mut inner = self.inner.exclusive_access();
-        let next_task = &mut inner.tasks[0];
-        next_task.task_status = TaskStatus::Running;
-        let next_task_cx_ptr = &next_task.task_cx as *const TaskContext;
-        drop(inner);
-        let mut _unused = TaskContext::zero_init();
-
-        trace!("Spawn first task");
-        self.update_task_first_run_time();
-        // before this, we should drop local variables that must be dropped manually
-        unsafe {
-            __switch(&mut _unused as *mut _, next_task_cx_ptr);
-        }
-        panic!("unreachable in run_first_task!");
-    }
-
-    /// Change the status of current `Running` task into `Ready`.
-    fn mark_current_suspended(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let cur = inner.current_task;
-        inner.tasks[cur].task_status = TaskStatus::Ready;
-    }
-
-    /// Change the status of current `Running` task into `Exited`.
-    fn mark_current_exited(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let cur = inner.current_task;
-        inner.tasks[cur].task_status = TaskStatus::Exited;
-    }
-
-    /// Find next task to run and return task id.
-    ///
-    /// In this case, we only return the first `Ready` task in task list.
-    fn find_next_task(&self) -> Option<usize> {
-        let inner = self.inner.exclusive_access();
-        let current = inner.current_task;
-        (current + 1..current + self.num_app + 1)
-            .map(|id| id % self.num_app)
-            .find(|id| inner.tasks[*id].task_status == TaskStatus::Ready)
-    }
-
-    /// Get the current 'Running' task's token.
-    fn get_current_token(&self) -> usize {
-        let inner = self.inner.exclusive_access();
-        inner.tasks[inner.current_task].get_user_token()
-    }
-
-    /// Get the current 'Running' task's trap contexts.
-    fn get_current_trap_cx(&self) -> &'static mut TrapContext {
-        let inner = self.inner.exclusive_access();
-        inner.tasks[inner.current_task].get_trap_cx()
-    }
-
-    /// Change the current 'Running' task's program break
-    pub fn change_current_program_brk(&self, size: i32) -> Option<usize> {
-        let mut inner = self.inner.exclusive_access();
-        let cur = inner.current_task;
-        inner.tasks[cur].change_program_brk(size)
-    }
-
-    /// Switch current `Running` task to the task we have found,
-    /// or there is no `Ready` task and we can exit with all applications completed
-    fn run_next_task(&self) {
-        if let Some(next) = self.find_next_task() {
-            self.kernel_timer_stop();
-
-            let mut inner = self.inner.exclusive_access();
-            let current = inner.current_task;
-            inner.tasks[next].task_status = TaskStatus::Running;
-            inner.current_task = next;
-            let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
-            let next_task_cx_ptr = &inner.tasks[next].task_cx as *const TaskContext;
-            drop(inner);
-
-            self.update_task_first_run_time();
-            // before this, we should drop local variables that must be dropped manually
-            unsafe {
-                __switch(current_task_cx_ptr, next_task_cx_ptr);
-            }
-
-            self.kernel_timer_start();
-            // go back to user mode
-        } else {
-            panic!("All applications completed!");
-        }
-    }
-
-    /// Get current task info
-    fn current_task_info(&self) -> (TaskStatus, TaskInfoBlock) {
-        let inner = self.inner.exclusive_access();
-        let current_task_no = inner.current_task;
-        let task_status = inner.tasks[current_task_no].task_status;
-        let task_info_block = inner.tasks[current_task_no].infos.clone();
-        (task_status, task_info_block)
-    }
-
-    /// Update syscall times
-    fn update_syscall_times(&self, syscall_id: usize) {
-        let mut inner = self.inner.exclusive_access();
-        let current_task_no = inner.current_task;
-        let syscall_times = &mut inner.tasks[current_task_no].infos.syscall_times;
-        *syscall_times.entry(syscall_id).or_default() += 1;
-    }
-
-    /// Start user timer
-    fn user_timer_start(&self) {
-        let inner = &mut *self.inner.exclusive_access();
-        let current_task_no = inner.current_task;
-        let now_us = get_time_us();
-
-        trace!("T[{current_task_no}] user timer start at {now_us}");
-
-        let timer_us = &mut inner.user_timer_us;
-
-        assert_eq!(*timer_us, 0, "timer start without reset.");
-
-        *timer_us = now_us;
-    }
-
-    /// Stop user timer
-    fn user_timer_stop(&self) {
-        let inner = &mut *self.inner.exclusive_access();
-        let current_task_no = inner.current_task;
-        let now_us = get_time_us();
-
-        trace!("T[{current_task_no}] user timer stop at {now_us}us");
-
-        let timer_us = &mut inner.user_timer_us;
-        let task_timer = &mut inner.tasks[current_task_no]
-            .infos
-            .running_times
-            .user_time_us;
-
-        assert_ne!(*timer_us, 0, "timer stop without set.");
-
-        let elapsed_us = now_us - *timer_us;
-        *task_timer += elapsed_us;
-        *timer_us = 0;
-    }
-
-    /// Start kernel timer
-    fn kernel_timer_start(&self) {
-        let inner = &mut *self.inner.exclusive_access();
-        let current_task_no = inner.current_task;
-        let now_us = get_time_us();
-
-        trace!("T[{current_task_no}] kernel timer start at {now_us}us");
-
-        let timer_us = &mut inner.kernel_timer_us;
-
-        assert_eq!(*timer_us, 0, "timer start without reset.");
-
-        *timer_us = now_us;
-    }
-
-    /// Stop kernel timer
-    fn kernel_timer_stop(&self) {
-        let inner = &mut *self.inner.exclusive_access();
-        let current_task_no = inner.current_task;
-        let now_us = get_time_us();
-
-        trace!("T[{current_task_no}] kernel timer stop at {now_us}us");
-
-        let timer_us = &mut inner.kernel_timer_us;
-        let task_timer = &mut inner.tasks[current_task_no]
-            .infos
-            .running_times
-            .kernel_time_us;
-
-        assert_ne!(*timer_us, 0, "timer stop without set.");
+/// Force `INITPROC`'s lazy initialization and enqueue it as the first task
+/// for the `Processor` to run. Called once at boot.
+pub fn add_initproc() {
+    add_task(Arc::clone(&INITPROC));
+}
 
-        let elapsed_us = now_us - *timer_us;
-        *task_timer += elapsed_us;
-        *timer_us = 0;
-    }
+/// Suspend the current 'Running' task and run the next task in task list.
+pub fn suspend_current_and_run_next() {
+    let task = take_current_task().unwrap();
 
-    /// Update task first run time info
-    fn update_task_first_run_time(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let current_task_no = inner.current_task;
+    let task_cx_ptr = {
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.task_status = TaskStatus::Ready;
+        &mut task_inner.task_cx as *mut TaskContext
+    };
 
-        let first_run_time_us = &mut inner.tasks[current_task_no]
-            .infos
-            .running_times
-            .first_run_time_us;
+    add_task(task);
+    schedule(task_cx_ptr);
+}
 
-        if *first_run_time_us == 0 {
-            let now_us = get_time_us();
-            trace!("T[{current_task_no}] first run at {now_us}us");
-            *first_run_time_us = now_us;
+/// Exit the current 'Running' task with `exit_code` and run the next task in
+/// task list. Every still-living child is reparented onto [`INITPROC`], the
+/// way pid 1 adopts orphans on a real Unix system.
+pub fn exit_current_and_run_next(exit_code: i32) {
+    let task = take_current_task().unwrap();
+    let pid = task.getpid();
 
-            drop(inner);
-            self.user_timer_start();
-        }
-    }
+    let mut inner = task.inner_exclusive_access();
+    inner.task_status = TaskStatus::Exited;
+    inner.exit_code = exit_code;
 
-    /// mmap
-    fn mmap(&self, addr: usize, len: usize, prot: usize) -> isize {
-        todo!()
+    for child in inner.children.iter() {
+        child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
+        INITPROC.inner_exclusive_access().children.push(Arc::clone(child));
     }
+    inner.children.clear();
+    drop(inner);
 
-    /// munmap
-    fn munmap(&self, addr: usize, len: usize) -> isize {
-        todo!()
-    }
-}
+    task.unmap_mmap_areas();
+    remove_from_pid2task(pid);
+    drop(task);
 
-/// Run the first task in task list.
-pub fn run_first_task() {
-    TASK_MANAGER.run_first_task();
+    let mut _unused = TaskContext::zero_init();
+    schedule(&mut _unused as *mut _);
+    panic!("unreachable in exit_current_and_run_next!");
 }
 
-/// Switch current `Running` task to the task we have found,
-/// or there is no `Ready` task and we can exit with all applications completed
-fn run_next_task() {
-    TASK_MANAGER.run_next_task();
+/// Change the current 'Running' task's program break
+pub fn change_program_brk(size: i32) -> Option<usize> {
+    current_task().unwrap().change_program_brk(size)
 }
 
-/// Change the status of current `Running` task into `Ready`.
-fn mark_current_suspended() {
-    TASK_MANAGER.mark_current_suspended();
+/// Set the priority of the current 'Running' task, clamped into the active
+/// `SchedulerPolicy`'s valid range. Returns the priority that was actually
+/// applied.
+pub fn set_priority(prio: isize) -> isize {
+    let priority = manager::normalize_priority(prio);
+    current_task().unwrap().inner_exclusive_access().priority = priority;
+    priority.get() as isize
 }
 
-/// Change the status of current `Running` task into `Exited`.
-fn mark_current_exited() {
-    TASK_MANAGER.mark_current_exited();
+/// Look up the seccomp action configured for `syscall_id` on the current task
+pub fn seccomp_action(syscall_id: usize) -> SeccompAction {
+    current_task().unwrap().inner_exclusive_access().seccomp.action(syscall_id)
 }
 
-/// Suspend the current 'Running' task and run the next task in task list.
-pub fn suspend_current_and_run_next() {
-    mark_current_suspended();
-    run_next_task();
+/// Install `action` for `syscall_id` in the current task's seccomp filter.
+/// Returns `false` if the filter is locked and `action` would loosen the
+/// existing entry.
+pub fn set_seccomp_action(syscall_id: usize, action: SeccompAction) -> bool {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .seccomp
+        .set_action(syscall_id, action)
 }
 
-/// Exit the current 'Running' task and run the next task in task list.
-pub fn exit_current_and_run_next() {
-    mark_current_exited();
-    run_next_task();
+/// Lock the current task's seccomp filter so it can only be tightened
+pub fn lock_seccomp() {
+    current_task().unwrap().inner_exclusive_access().seccomp.lock();
 }
 
-/// Get the current 'Running' task's token.
-pub fn current_user_token() -> usize {
-    TASK_MANAGER.get_current_token()
+/// Whether the current task is allowed to make `syscall_id`
+pub fn syscall_allowed(syscall_id: usize) -> bool {
+    current_task().unwrap().inner_exclusive_access().capabilities.is_allowed(syscall_id)
 }
 
-/// Get the current 'Running' task's trap contexts.
-pub fn current_trap_cx() -> &'static mut TrapContext {
-    TASK_MANAGER.get_current_trap_cx()
+/// Narrow the current task's syscall capability set by intersecting it with
+/// `mask`
+pub fn restrict_syscalls(mask: &[u64; CAPABILITY_MASK_WORDS]) {
+    current_task().unwrap().inner_exclusive_access().capabilities.restrict(mask);
 }
 
-/// Change the current 'Running' task's program break
-pub fn change_program_brk(size: i32) -> Option<usize> {
-    TASK_MANAGER.change_current_program_brk(size)
+/// Raise `flag` against the current task
+pub fn raise_current_signal(flag: SignalFlags) {
+    current_task().unwrap().inner_exclusive_access().signals.insert(flag);
 }
 
 /// Get current task info
 pub fn current_task_info() -> (TaskStatus, TaskInfoBlock) {
-    TASK_MANAGER.current_task_info()
+    current_task().unwrap().inner_exclusive_access().task_info()
 }
 
 /// Update syscall_times
 pub fn update_syscall_times(syscall_id: usize) {
-    TASK_MANAGER.update_syscall_times(syscall_id);
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .update_syscall_times(syscall_id);
 }
 
-/// Start user timer
-pub fn user_timer_start() {
-    TASK_MANAGER.user_timer_start();
+/// Record one invocation of `syscall_id` against the current task's syscall
+/// trace
+pub fn record_syscall_trace(syscall_id: usize, args: [usize; 4], elapsed_us: u64) {
+    current_task()
+        .unwrap()
+        .inner_exclusive_access()
+        .trace
+        .record(syscall_id, args, elapsed_us);
 }
 
-/// Stop user timer
-pub fn user_timer_stop() {
-    TASK_MANAGER.user_timer_stop();
+/// Snapshot of the current task's syscall trace
+pub fn syscall_trace() -> SyscallTrace {
+    current_task().unwrap().inner_exclusive_access().trace.clone()
 }
 
-/// Start kernel timer
-pub fn kernel_timer_start() {
-    TASK_MANAGER.kernel_timer_start();
-}
+/// Create an anonymous, demand-framed mapping in the current task's address
+/// space. Rejects a misaligned `addr`, an empty `len`, a `prot` with bits
+/// outside R/W/X or with no permission bit set at all, and any overlap with
+/// an already-mapped page.
+pub fn mmap(addr: usize, len: usize, prot: usize) -> isize {
+    const PROT_MASK: usize = 0b111;
+    if addr % PAGE_SIZE != 0 || len == 0 || (prot & !PROT_MASK) != 0 || (prot & PROT_MASK) == 0 {
+        return -1;
+    }
+    let len = len.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+    let start_va = VirtAddr::from(addr);
+    let end_va = VirtAddr::from(addr + len);
+    let start_vpn = start_va.floor();
+    let end_vpn = end_va.ceil();
+
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    for vpn in start_vpn.0..end_vpn.0 {
+        if task_inner
+            .memory_set
+            .translate(VirtPageNum(vpn))
+            .is_some_and(|pte| pte.is_valid())
+        {
+            return -1;
+        }
+    }
 
-/// Stop kernel timer
-pub fn kernel_timer_stop() {
-    TASK_MANAGER.kernel_timer_stop();
-}
+    let mut permission = MapPermission::U;
+    if prot & 0b001 != 0 {
+        permission |= MapPermission::R;
+    }
+    if prot & 0b010 != 0 {
+        permission |= MapPermission::W;
+    }
+    if prot & 0b100 != 0 {
+        permission |= MapPermission::X;
+    }
 
-/// Create memory map for user space
-pub fn mmap(addr: usize, len: usize, prot: usize) -> isize {
-    TASK_MANAGER.mmap(addr, len, prot)
+    task_inner
+        .memory_set
+        .insert_framed_area(start_va, end_va, permission);
+    task_inner.mmap_areas.push((start_va, end_va));
+    0
 }
 
-/// Remove memory map for user space
+/// Remove a mapping previously installed by `mmap`, failing unless the
+/// whole page-aligned range is currently mapped.
 pub fn munmap(addr: usize, len: usize) -> isize {
-    TASK_MANAGER.munmap(addr, len)
+    if addr % PAGE_SIZE != 0 || len == 0 {
+        return -1;
+    }
+    let len = len.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+    let start_va = VirtAddr::from(addr);
+    let end_va = VirtAddr::from(addr + len);
+    let start_vpn = start_va.floor();
+    let end_vpn = end_va.ceil();
+
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    for vpn in start_vpn.0..end_vpn.0 {
+        if !task_inner
+            .memory_set
+            .translate(VirtPageNum(vpn))
+            .is_some_and(|pte| pte.is_valid())
+        {
+            return -1;
+        }
+    }
+
+    task_inner.memory_set.remove_area_with_start_vpn(start_vpn);
+    task_inner.mmap_areas.retain(|&(s, _)| s != start_va);
+    0
 }
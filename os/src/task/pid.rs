@@ -0,0 +1,92 @@
+//! Pid allocation and the pid -> task lookup table
+
+use super::TaskControlBlock;
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+struct PidAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl PidAllocator {
+    pub fn new() -> Self {
+        Self {
+            current: 0,
+            recycled: Vec::new(),
+        }
+    }
+
+    pub fn alloc(&mut self) -> PidHandle {
+        if let Some(pid) = self.recycled.pop() {
+            PidHandle(pid)
+        } else {
+            self.current += 1;
+            PidHandle(self.current - 1)
+        }
+    }
+
+    pub fn dealloc(&mut self, pid: usize) {
+        assert!(pid < self.current);
+        assert!(
+            !self.recycled.iter().any(|ppid| *ppid == pid),
+            "pid {} has been deallocated!",
+            pid
+        );
+        self.recycled.push(pid);
+    }
+}
+
+lazy_static! {
+    static ref PID_ALLOCATOR: UPSafeCell<PidAllocator> =
+        unsafe { UPSafeCell::new(PidAllocator::new()) };
+}
+
+/// An allocated pid, freed back to the allocator on drop
+pub struct PidHandle(pub usize);
+
+impl Drop for PidHandle {
+    fn drop(&mut self) {
+        PID_ALLOCATOR.exclusive_access().dealloc(self.0);
+    }
+}
+
+/// Allocate a new pid
+pub fn pid_alloc() -> PidHandle {
+    PID_ALLOCATOR.exclusive_access().alloc()
+}
+
+lazy_static! {
+    static ref PID2TCB: UPSafeCell<BTreeMap<usize, Arc<TaskControlBlock>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Look up the task with the given pid, if it is still registered
+pub fn pid2task(pid: usize) -> Option<Arc<TaskControlBlock>> {
+    let map = PID2TCB.exclusive_access();
+    map.get(&pid).cloned()
+}
+
+/// Register `task` under `pid`
+pub fn insert_into_pid2task(pid: usize, task: Arc<TaskControlBlock>) {
+    PID2TCB.exclusive_access().insert(pid, task);
+}
+
+/// Remove `pid`'s registration, once its task has exited
+pub fn remove_from_pid2task(pid: usize) {
+    let mut map = PID2TCB.exclusive_access();
+    assert!(
+        map.remove(&pid).is_some(),
+        "cannot find pid {} in pid2task!",
+        pid
+    );
+}
+
+/// Every currently-registered task, e.g. for `sys_kill`'s negative-pid
+/// process-group broadcast.
+pub fn all_tasks() -> Vec<Arc<TaskControlBlock>> {
+    PID2TCB.exclusive_access().values().cloned().collect()
+}
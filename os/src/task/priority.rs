@@ -16,6 +16,14 @@ impl<T> PriorityImpl<T> {
     pub fn new(value: T) -> Self {
         Self(value)
     }
+
+    /// The raw priority value
+    pub fn get(&self) -> T
+    where
+        T: Copy,
+    {
+        self.0
+    }
 }
 
 impl<T> Default for PriorityImpl<T>
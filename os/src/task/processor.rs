@@ -7,7 +7,7 @@
 use super::__switch;
 use super::task::TaskInfoBlock;
 use super::{fetch_task, TaskStatus};
-use super::{TaskContext, TaskControlBlock};
+use super::{SignalFlags, TaskContext, TaskControlBlock};
 use crate::sync::UPSafeCell;
 use crate::timer::get_time_us;
 use crate::trap::TrapContext;
@@ -49,10 +49,25 @@ impl Processor {
     ///Get current task in moving semanteme
     pub fn take_current(&mut self) -> Option<Arc<TaskControlBlock>> {
         self.kernel_timer_stop();
+        self.check_current_itimer();
 
         self.current.take()
     }
 
+    /// Check the current task's `ITIMER_REAL` timer against the clock and
+    /// raise `SIGALRM` if it has expired. Called every time control leaves a
+    /// task (i.e. on every timer interrupt that reschedules), which is
+    /// close enough to "on each timer interrupt" for a non-preemptive-within-quantum
+    /// scheduler.
+    fn check_current_itimer(&self) {
+        if let Some(task) = self.current_ref() {
+            let mut inner = task.inner_exclusive_access();
+            if inner.timer.check_expired(get_time_us()) {
+                inner.signals.insert(SignalFlags::SIGALRM);
+            }
+        }
+    }
+
     ///Get current task in cloning semanteme
     pub fn current(&self) -> Option<Arc<TaskControlBlock>> {
         self.current.as_ref().map(Arc::clone)
@@ -280,22 +295,3 @@ pub fn update_syscall_times(syscall_id: usize) {
         .update_syscall_times(syscall_id);
 }
 
-/// Create memory map for user space
-pub fn mmap(addr: usize, len: usize, prot: usize) -> isize {
-    PROCESSOR
-        .exclusive_access()
-        .current_ref()
-        .unwrap()
-        .inner_exclusive_access()
-        .mmap(addr, len, prot)
-}
-
-/// Remove memory map for user space
-pub fn munmap(addr: usize, len: usize) -> isize {
-    PROCESSOR
-        .exclusive_access()
-        .current_ref()
-        .unwrap()
-        .inner_exclusive_access()
-        .munmap(addr, len)
-}
@@ -0,0 +1,102 @@
+//! Per-task resource limits (`rlimit`/`prlimit` style)
+
+/// A soft/hard resource-limit pair, mirroring `struct rlimit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rlimit {
+    pub soft: usize,
+    pub hard: usize,
+}
+
+impl Rlimit {
+    /// No limit at all.
+    pub const UNLIMITED: Self = Self {
+        soft: usize::MAX,
+        hard: usize::MAX,
+    };
+}
+
+/// Which resource a `Rlimit` bounds. Only the resources this kernel
+/// enforces are modeled; unknown ids are rejected by `Resource::from_id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// Size of the process's data/heap segment, checked in `sys_sbrk`
+    Data,
+    /// Total virtual address space mapped, checked in `sys_mmap`
+    AddressSpace,
+    /// Number of processes the owner may have alive at once
+    Nproc,
+}
+
+/// `RLIMIT_DATA`, matching the Linux resource id
+pub const RLIMIT_DATA: usize = 2;
+/// `RLIMIT_NPROC`, matching the Linux resource id
+pub const RLIMIT_NPROC: usize = 6;
+/// `RLIMIT_AS`, matching the Linux resource id
+pub const RLIMIT_AS: usize = 9;
+
+impl Resource {
+    /// Map a raw `prlimit(2)`-style resource id to a `Resource`.
+    pub fn from_id(id: usize) -> Option<Self> {
+        match id {
+            RLIMIT_DATA => Some(Resource::Data),
+            RLIMIT_AS => Some(Resource::AddressSpace),
+            RLIMIT_NPROC => Some(Resource::Nproc),
+            _ => None,
+        }
+    }
+}
+
+/// A task's resource-limit table, copied verbatim into any `fork`/`spawn`
+/// child.
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceLimits {
+    data: Rlimit,
+    address_space: Rlimit,
+    nproc: Rlimit,
+}
+
+impl ResourceLimits {
+    /// Every resource starts unbounded.
+    pub const fn unlimited() -> Self {
+        Self {
+            data: Rlimit::UNLIMITED,
+            address_space: Rlimit::UNLIMITED,
+            nproc: Rlimit::UNLIMITED,
+        }
+    }
+
+    /// The current soft/hard pair for `resource`.
+    pub fn get(&self, resource: Resource) -> Rlimit {
+        match resource {
+            Resource::Data => self.data,
+            Resource::AddressSpace => self.address_space,
+            Resource::Nproc => self.nproc,
+        }
+    }
+
+    /// Set `resource`'s limit, clamping `soft` to `hard`. Only a
+    /// `privileged` caller may raise the hard limit above its current
+    /// value; an unprivileged attempt to do so is rejected entirely.
+    pub fn set(&mut self, resource: Resource, new: Rlimit, privileged: bool) -> bool {
+        let current = self.get(resource);
+        if !privileged && new.hard > current.hard {
+            return false;
+        }
+        let clamped = Rlimit {
+            soft: new.soft.min(new.hard),
+            hard: new.hard,
+        };
+        *match resource {
+            Resource::Data => &mut self.data,
+            Resource::AddressSpace => &mut self.address_space,
+            Resource::Nproc => &mut self.nproc,
+        } = clamped;
+        true
+    }
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
@@ -0,0 +1,99 @@
+//! Pluggable scheduling policy for the ready-queue `TaskManager`
+//!
+//! `SchedulerPolicy` factors "which `Ready` task runs next" and "how a
+//! task's priority affects its turn" out of `TaskManager`, so swapping one
+//! algorithm for another doesn't touch `TaskManager::fetch` itself.
+
+use super::priority::Priority;
+use super::stride::Stride;
+
+/// Per-task bookkeeping owned by whichever `SchedulerPolicy` is active.
+/// `Fifo` never looks at it; `StrideScheduler` uses it to track accumulated
+/// stride.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SchedulerState {
+    pub stride: Stride,
+}
+
+/// A `Ready` task as seen by a `SchedulerPolicy`, i.e. everything it needs to
+/// decide whether this task should run next.
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate {
+    pub id: usize,
+    pub state: SchedulerState,
+    pub priority: Priority,
+}
+
+/// A pluggable CPU scheduling algorithm for the ready-queue `TaskManager`.
+pub trait SchedulerPolicy {
+    /// Called when a task transitions into `Ready`, before it is eligible to
+    /// be picked by `pick_next`.
+    fn on_enqueue(&self, state: &mut SchedulerState);
+
+    /// Choose which of `candidates` (every currently `Ready` task) should run
+    /// next, returning its id.
+    fn pick_next(&self, candidates: &[Candidate]) -> Option<usize>;
+
+    /// Account for the time slice about to be spent running a task, called
+    /// right before it is switched onto.
+    fn on_tick(&self, state: &mut SchedulerState, priority: Priority);
+
+    /// Map a requested nice-like value into this policy's valid priority
+    /// range, clamping rather than rejecting so the same priority request
+    /// is portable across policies.
+    fn normalize_priority(&self, requested: isize) -> Priority;
+}
+
+/// Round-robin FIFO: `Ready` tasks run in ascending id order, ignoring
+/// priority entirely.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fifo;
+
+impl SchedulerPolicy for Fifo {
+    fn on_enqueue(&self, _state: &mut SchedulerState) {}
+
+    fn pick_next(&self, candidates: &[Candidate]) -> Option<usize> {
+        candidates.iter().map(|c| c.id).min()
+    }
+
+    fn on_tick(&self, _state: &mut SchedulerState, _priority: Priority) {}
+
+    fn normalize_priority(&self, _requested: isize) -> Priority {
+        Priority::default()
+    }
+}
+
+/// Stride scheduling: among every `Ready` task, the one with the smallest
+/// accumulated stride runs next, so a task's share of the CPU is governed by
+/// its priority rather than turn order.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StrideScheduler;
+
+/// The valid priority range this policy accepts; `normalize_priority` clamps
+/// any requested value into it instead of failing.
+const MIN_PRIORITY: isize = 2;
+const MAX_PRIORITY: isize = i32::MAX as isize;
+
+impl SchedulerPolicy for StrideScheduler {
+    fn on_enqueue(&self, _state: &mut SchedulerState) {}
+
+    fn pick_next(&self, candidates: &[Candidate]) -> Option<usize> {
+        candidates
+            .iter()
+            .min_by_key(|c| (c.state.stride, c.id))
+            .map(|c| c.id)
+    }
+
+    fn on_tick(&self, state: &mut SchedulerState, priority: Priority) {
+        state.stride.step(priority);
+    }
+
+    fn normalize_priority(&self, requested: isize) -> Priority {
+        let clamped = requested.clamp(MIN_PRIORITY, MAX_PRIORITY);
+        Priority::try_from(clamped).unwrap()
+    }
+}
+
+/// The scheduling algorithm `TASK_MANAGER` runs. Swap this alias to change
+/// policy without touching `TaskManager` itself.
+pub type ActivePolicy = StrideScheduler;
@@ -0,0 +1,83 @@
+//! Seccomp-style per-task syscall filtering
+
+use crate::config::MAX_SYSCALL_NUM;
+
+/// The action to take when a filtered syscall is invoked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeccompAction {
+    /// Run the syscall handler normally.
+    Allow,
+    /// Skip the handler and return the wrapped value instead.
+    Errno(isize),
+    /// Terminate the task immediately, as if it had faulted.
+    Kill,
+    /// Deliver a signal to the task instead of running the handler.
+    Trap,
+}
+
+impl SeccompAction {
+    /// How restrictive an action is, used to enforce the `no_new_privs`
+    /// lock: a locked filter may only be replaced by an action that is at
+    /// least this restrictive.
+    fn rank(self) -> u8 {
+        match self {
+            SeccompAction::Allow => 0,
+            SeccompAction::Errno(_) => 1,
+            SeccompAction::Trap => 2,
+            SeccompAction::Kill => 3,
+        }
+    }
+}
+
+/// A task's seccomp filter: one action per syscall id, plus a
+/// `no_new_privs`-style lock. Once locked, a filter can only be tightened,
+/// never loosened, so a task can sandbox itself (and its children) without
+/// being able to undo the sandbox later.
+#[derive(Debug, Clone, Copy)]
+pub struct SeccompFilter {
+    actions: [SeccompAction; MAX_SYSCALL_NUM],
+    locked: bool,
+}
+
+impl SeccompFilter {
+    /// An empty filter: every syscall is allowed and the lock is open.
+    pub const fn new() -> Self {
+        Self {
+            actions: [SeccompAction::Allow; MAX_SYSCALL_NUM],
+            locked: false,
+        }
+    }
+
+    /// Look up the action configured for `syscall_id`. Ids past the end of
+    /// the table (such as `SYSCALL_SECCOMP` itself) are always allowed.
+    pub fn action(&self, syscall_id: usize) -> SeccompAction {
+        self.actions
+            .get(syscall_id)
+            .copied()
+            .unwrap_or(SeccompAction::Allow)
+    }
+
+    /// Install `action` for `syscall_id`. Returns `false` without making any
+    /// change if the filter is locked and `action` would loosen the
+    /// existing entry.
+    pub fn set_action(&mut self, syscall_id: usize, action: SeccompAction) -> bool {
+        if self.locked && action.rank() < self.actions[syscall_id].rank() {
+            return false;
+        }
+        self.actions[syscall_id] = action;
+        true
+    }
+
+    /// Lock the filter so it can only be tightened from now on. A `fork`ed
+    /// or `spawn`ed child inherits both the filter entries and this lock,
+    /// since `TaskControlBlock` simply copies its `seccomp` field.
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+}
+
+impl Default for SeccompFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,78 @@
+//! Signal numbers delivered to tasks
+
+use bitflags::bitflags;
+
+bitflags! {
+    /// The set of signals a task can have pending against it.
+    #[derive(Default)]
+    pub struct SignalFlags: u32 {
+        const SIGHUP    = 1 << 1;
+        const SIGINT    = 1 << 2;
+        const SIGQUIT   = 1 << 3;
+        const SIGILL    = 1 << 4;
+        const SIGTRAP   = 1 << 5;
+        const SIGABRT   = 1 << 6;
+        const SIGBUS    = 1 << 7;
+        const SIGFPE    = 1 << 8;
+        const SIGKILL   = 1 << 9;
+        const SIGUSR1   = 1 << 10;
+        const SIGSEGV   = 1 << 11;
+        const SIGUSR2   = 1 << 12;
+        const SIGPIPE   = 1 << 13;
+        const SIGALRM   = 1 << 14;
+        const SIGTERM   = 1 << 15;
+        const SIGSTKFLT = 1 << 16;
+        const SIGCHLD   = 1 << 17;
+        const SIGCONT   = 1 << 18;
+        const SIGSTOP   = 1 << 19;
+        const SIGTSTP   = 1 << 20;
+        const SIGTTIN   = 1 << 21;
+        const SIGTTOU   = 1 << 22;
+        const SIGURG    = 1 << 23;
+        const SIGXCPU   = 1 << 24;
+        const SIGXFSZ   = 1 << 25;
+        const SIGVTALRM = 1 << 26;
+        const SIGPROF   = 1 << 27;
+        const SIGWINCH  = 1 << 28;
+        const SIGIO     = 1 << 29;
+        const SIGPWR    = 1 << 30;
+        const SIGSYS    = 1 << 31;
+    }
+}
+
+/// The largest signal number in use.
+pub const MAX_SIG: usize = 31;
+
+/// A task's disposition for one signal: the user-space handler to run (`0`
+/// for the default action) and the mask to apply while it runs, mirroring
+/// `struct sigaction`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub mask: SignalFlags,
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: 0,
+            mask: SignalFlags::empty(),
+        }
+    }
+}
+
+/// A task's full signal disposition table, one [`SignalAction`] per signal
+/// number, copied verbatim into any `fork`ed child.
+#[derive(Clone)]
+pub struct SignalActions {
+    pub table: [SignalAction; MAX_SIG + 1],
+}
+
+impl Default for SignalActions {
+    fn default() -> Self {
+        Self {
+            table: [SignalAction::default(); MAX_SIG + 1],
+        }
+    }
+}
@@ -1,15 +1,367 @@
 //! Types related to task management
 
+use super::capabilities::SyscallCapabilities;
+use super::itimer::ItimerState;
+use super::kernel_stack::KernelStack;
+use super::pid::{pid_alloc, PidHandle};
+use super::priority::Priority;
+use super::rlimit::ResourceLimits;
+use super::scheduler::SchedulerState;
+use super::seccomp::SeccompFilter;
+use super::signal::{SignalActions, SignalFlags};
+use super::trace::SyscallTrace;
 use super::TaskContext;
-use crate::config::MAX_SYSCALL_NUM;
+use crate::config::{MAX_SYSCALL_NUM, TRAP_CONTEXT_BASE};
+use crate::mm::{MemorySet, PhysPageNum, VirtAddr};
+use crate::sync::UPSafeCell;
+use crate::trap::TrapContext;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::cell::RefMut;
 
 /// The task control block (TCB) of a task.
-#[derive(Copy, Clone, Debug)]
+///
+/// `pid` and `kernel_stack` are fixed for this task's whole lifetime and
+/// read without locking; everything that changes over that lifetime lives
+/// behind `inner`.
 pub struct TaskControlBlock {
-    /// The task status in it's lifecycle
+    /// This task's pid, recycled back to the allocator once dropped
+    pub pid: PidHandle,
+    /// This task's kernel-mode stack, mapped at a slot indexed by `pid`
+    pub kernel_stack: KernelStack,
+    inner: UPSafeCell<TaskControlBlockInner>,
+}
+
+/// Everything about a task that changes over its lifetime.
+pub struct TaskControlBlockInner {
+    pub trap_cx_ppn: PhysPageNum,
+    pub base_size: usize,
     pub task_status: TaskStatus,
-    /// The task context
     pub task_cx: TaskContext,
+    /// This task's scheduling priority, set through `sys_set_priority`
+    pub priority: Priority,
+    /// This task's bookkeeping for whichever `SchedulerPolicy` is active
+    pub scheduler: SchedulerState,
+    /// This task's seccomp filter, installed through `sys_seccomp` and
+    /// copied verbatim into any child derived from this task
+    pub seccomp: SeccompFilter,
+    /// This task's syscall capability set, narrowed through
+    /// `sys_restrict_syscalls` and copied verbatim into any child derived
+    /// from this task
+    pub capabilities: SyscallCapabilities,
+    /// This task's address space
+    pub memory_set: MemorySet,
+    /// `[start, end)` ranges installed through `sys_mmap`, torn down when
+    /// this task exits
+    pub mmap_areas: Vec<(VirtAddr, VirtAddr)>,
+    /// Total bytes currently mapped through `sys_mmap`, checked against
+    /// `rlimits`' `RLIMIT_AS` on every new mapping
+    pub mapped_bytes: usize,
+    pub heap_bottom: usize,
+    pub program_brk: usize,
+    pub parent: Option<Weak<TaskControlBlock>>,
+    pub children: Vec<Arc<TaskControlBlock>>,
+    /// This task's exit code, valid once `task_status` is `Exited`
+    pub exit_code: i32,
+    /// Signals raised against this task but not yet handled
+    pub signals: SignalFlags,
+    /// Signals this task has blocked via `sys_sigprocmask`
+    pub signal_mask: SignalFlags,
+    /// This task's `sigaction(2)`-style disposition table
+    pub signal_actions: SignalActions,
+    /// The signal currently being handled, or `-1` if none
+    pub handling_sig: isize,
+    /// The trap context saved before entering a signal handler, restored by
+    /// `sys_sigreturn`
+    pub trap_ctx_backup: Option<TrapContext>,
+    /// This task's process group id
+    pub pgid: usize,
+    /// This task's session id
+    pub sid: usize,
+    /// This task's `ITIMER_REAL` timer
+    pub timer: ItimerState,
+    /// This task's resource limits, copied verbatim into any child
+    pub rlimits: ResourceLimits,
+    /// CPU time billed onto this task from children already reaped through
+    /// `sys_waitpid`
+    pub children_running_times: RunningTimeInfo,
+    pub infos: TaskInfoBlock,
+    /// This task's syscall accounting, read back through `sys_syscall_trace`;
+    /// unlike `seccomp`/`rlimits`, a fresh task starts with an empty trace
+    /// rather than inheriting its parent's
+    pub trace: SyscallTrace,
+}
+
+impl TaskControlBlockInner {
+    /// The trap context of this task, mapped into its own address space at
+    /// `TRAP_CONTEXT_BASE`.
+    pub fn get_trap_cx(&self) -> &'static mut TrapContext {
+        self.trap_cx_ppn.get_mut()
+    }
+
+    /// The token identifying this task's page table, for `satp`/translation
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+
+    pub fn is_zombie(&self) -> bool {
+        self.task_status == TaskStatus::Exited
+    }
+
+    /// Change this task's program break by `size` bytes, failing if it
+    /// would move below `heap_bottom`.
+    pub fn change_program_brk(&mut self, size: i32) -> Option<usize> {
+        let old_brk = self.program_brk;
+        let new_brk = self.program_brk as isize + size as isize;
+        if new_brk < self.heap_bottom as isize {
+            return None;
+        }
+        let result = if size < 0 {
+            self.memory_set
+                .shrink_to(VirtAddr::from(self.heap_bottom), VirtAddr::from(new_brk as usize))
+        } else {
+            self.memory_set
+                .append_to(VirtAddr::from(self.heap_bottom), VirtAddr::from(new_brk as usize))
+        };
+        if result {
+            self.program_brk = new_brk as usize;
+            Some(old_brk)
+        } else {
+            None
+        }
+    }
+
+    /// Get current task info
+    pub fn task_info(&self) -> (TaskStatus, TaskInfoBlock) {
+        (self.task_status, self.infos.clone())
+    }
+
+    /// Update syscall times
+    pub fn update_syscall_times(&mut self, syscall_id: usize) {
+        *self.infos.syscall_times.entry(syscall_id).or_default() += 1;
+    }
+
+    /// Set this task's scheduling priority
+    pub fn set_priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
+}
+
+impl TaskControlBlock {
+    /// Exclusive access to this task's mutable state
+    pub fn inner_exclusive_access(&self) -> RefMut<'_, TaskControlBlockInner> {
+        self.inner.exclusive_access()
+    }
+
+    /// This task's pid
+    pub fn getpid(&self) -> usize {
+        self.pid.0
+    }
+
+    /// The token identifying this task's page table
+    pub fn get_user_token(&self) -> usize {
+        self.inner_exclusive_access().get_user_token()
+    }
+
+    /// Change this task's program break by `size` bytes
+    pub fn change_program_brk(&self, size: i32) -> Option<usize> {
+        self.inner_exclusive_access().change_program_brk(size)
+    }
+
+    /// Build the initial process from an ELF image.
+    pub fn new(elf_data: &[u8], app_id: usize) -> Self {
+        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT_BASE).into())
+            .unwrap()
+            .ppn();
+        let pid_handle = pid_alloc();
+        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack_top = kernel_stack.top();
+        let task_control_block = Self {
+            pid: pid_handle,
+            kernel_stack,
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    trap_cx_ppn,
+                    base_size: user_sp,
+                    task_status: TaskStatus::Ready,
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    priority: Priority::default(),
+                    scheduler: SchedulerState::default(),
+                    seccomp: SeccompFilter::default(),
+                    capabilities: SyscallCapabilities::default(),
+                    memory_set,
+                    mmap_areas: Vec::new(),
+                    mapped_bytes: 0,
+                    heap_bottom: user_sp,
+                    program_brk: user_sp,
+                    parent: None,
+                    children: Vec::new(),
+                    exit_code: 0,
+                    signals: SignalFlags::empty(),
+                    signal_mask: SignalFlags::empty(),
+                    signal_actions: SignalActions::default(),
+                    handling_sig: -1,
+                    trap_ctx_backup: None,
+                    pgid: app_id,
+                    sid: app_id,
+                    timer: ItimerState::disarmed(),
+                    rlimits: ResourceLimits::default(),
+                    children_running_times: RunningTimeInfo::default(),
+                    infos: TaskInfoBlock::new(),
+                    trace: SyscallTrace::new(),
+                })
+            },
+        };
+        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(entry_point, user_sp);
+        task_control_block
+    }
+
+    /// Duplicate this task into a new child whose address space is an
+    /// eager copy of this task's (no copy-on-write sharing). Used by
+    /// `sys_fork`.
+    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+        let mut parent_inner = self.inner_exclusive_access();
+        let memory_set = MemorySet::from_existing_user(&parent_inner.memory_set);
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT_BASE).into())
+            .unwrap()
+            .ppn();
+        let pid_handle = pid_alloc();
+        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack_top = kernel_stack.top();
+        let task_control_block = Arc::new(Self {
+            pid: pid_handle,
+            kernel_stack,
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    trap_cx_ppn,
+                    base_size: parent_inner.base_size,
+                    task_status: TaskStatus::Ready,
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    priority: parent_inner.priority,
+                    scheduler: SchedulerState::default(),
+                    seccomp: parent_inner.seccomp,
+                    capabilities: parent_inner.capabilities,
+                    memory_set,
+                    mmap_areas: parent_inner.mmap_areas.clone(),
+                    mapped_bytes: parent_inner.mapped_bytes,
+                    heap_bottom: parent_inner.heap_bottom,
+                    program_brk: parent_inner.program_brk,
+                    parent: Some(Arc::downgrade(self)),
+                    children: Vec::new(),
+                    exit_code: 0,
+                    signals: SignalFlags::empty(),
+                    signal_mask: parent_inner.signal_mask,
+                    signal_actions: parent_inner.signal_actions.clone(),
+                    handling_sig: -1,
+                    trap_ctx_backup: None,
+                    pgid: parent_inner.pgid,
+                    sid: parent_inner.sid,
+                    timer: ItimerState::disarmed(),
+                    rlimits: parent_inner.rlimits,
+                    children_running_times: RunningTimeInfo::default(),
+                    infos: TaskInfoBlock::new(),
+                    trace: SyscallTrace::new(),
+                })
+            },
+        });
+        parent_inner.children.push(Arc::clone(&task_control_block));
+        task_control_block
+    }
+
+    /// Replace this task's address space with a fresh ELF image, keeping
+    /// its pid, parent and children. Used by `sys_exec`.
+    ///
+    /// `args` is accepted for API symmetry with `execve(2)`; this kernel's
+    /// user programs take no argv, so it is only used for the `argc`
+    /// returned in `a0`.
+    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) {
+        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT_BASE).into())
+            .unwrap()
+            .ppn();
+
+        let mut inner = self.inner_exclusive_access();
+        inner.memory_set = memory_set;
+        inner.trap_cx_ppn = trap_cx_ppn;
+        inner.base_size = user_sp;
+        inner.heap_bottom = user_sp;
+        inner.program_brk = user_sp;
+        inner.mmap_areas.clear();
+        inner.mapped_bytes = 0;
+        let trap_cx = inner.get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(entry_point, user_sp);
+        trap_cx.x[10] = args.len();
+    }
+
+    /// Load `elf_data` as a brand-new child of this task, combining `fork`'s
+    /// pid/parent-child bookkeeping with `exec`'s fresh address space. Used
+    /// by `sys_spawn`.
+    pub fn spawn(self: &Arc<Self>, elf_data: &[u8]) -> Arc<Self> {
+        let mut parent_inner = self.inner_exclusive_access();
+        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT_BASE).into())
+            .unwrap()
+            .ppn();
+        let pid_handle = pid_alloc();
+        let kernel_stack = KernelStack::new(&pid_handle);
+        let kernel_stack_top = kernel_stack.top();
+        let task_control_block = Arc::new(Self {
+            pid: pid_handle,
+            kernel_stack,
+            inner: unsafe {
+                UPSafeCell::new(TaskControlBlockInner {
+                    trap_cx_ppn,
+                    base_size: user_sp,
+                    task_status: TaskStatus::Ready,
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    priority: Priority::default(),
+                    scheduler: SchedulerState::default(),
+                    seccomp: parent_inner.seccomp,
+                    capabilities: parent_inner.capabilities,
+                    memory_set,
+                    mmap_areas: Vec::new(),
+                    mapped_bytes: 0,
+                    heap_bottom: user_sp,
+                    program_brk: user_sp,
+                    parent: Some(Arc::downgrade(self)),
+                    children: Vec::new(),
+                    exit_code: 0,
+                    signals: SignalFlags::empty(),
+                    signal_mask: SignalFlags::empty(),
+                    signal_actions: SignalActions::default(),
+                    handling_sig: -1,
+                    trap_ctx_backup: None,
+                    pgid: parent_inner.pgid,
+                    sid: parent_inner.sid,
+                    timer: ItimerState::disarmed(),
+                    rlimits: parent_inner.rlimits,
+                    children_running_times: RunningTimeInfo::default(),
+                    infos: TaskInfoBlock::new(),
+                    trace: SyscallTrace::new(),
+                })
+            },
+        });
+        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(entry_point, user_sp);
+        parent_inner.children.push(Arc::clone(&task_control_block));
+        task_control_block
+    }
+
+    /// Unmap every region installed through `sys_mmap`. Called when this
+    /// task exits so it does not leak mapped frames.
+    pub fn unmap_mmap_areas(&self) {
+        let mut inner = self.inner_exclusive_access();
+        for (start, _) in core::mem::take(&mut inner.mmap_areas) {
+            inner.memory_set.remove_area_with_start_vpn(start.floor());
+        }
+    }
 }
 
 /// The running time info of task
@@ -20,8 +372,18 @@ pub struct RunningTimeInfo {
     pub real_time_us: usize,
 }
 
+impl RunningTimeInfo {
+    /// Fold `child`'s accumulated times into `self`, the way a parent bills
+    /// a reaped child's CPU usage onto its own `RUSAGE_CHILDREN` total.
+    pub fn accumulate(&mut self, child: &Self) {
+        self.user_time_us += child.user_time_us;
+        self.kernel_time_us += child.kernel_time_us;
+        self.real_time_us += child.real_time_us;
+    }
+}
+
 /// The task information block of a task.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TaskInfoBlock {
     pub syscall_times: [u32; MAX_SYSCALL_NUM],
     pub running_times: RunningTimeInfo,
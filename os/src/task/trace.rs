@@ -0,0 +1,50 @@
+//! Per-task syscall tracing and accounting
+//!
+//! Generalizes the reentrancy bookkeeping `SyscallChecker` already does and
+//! the `update_syscall_times` hook into one per-task accounting layer: how
+//! many times this task has called each syscall, how much cumulative time
+//! it has spent inside each one, and the arguments of its most recent call.
+
+use crate::config::MAX_SYSCALL_NUM;
+
+/// Accounting for a single syscall id, as seen by one task.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyscallStats {
+    pub count: u64,
+    pub total_time_us: u64,
+    pub last_args: [usize; 4],
+}
+
+/// One task's full syscall trace, indexed by syscall id, copied out to
+/// userspace whole through `sys_syscall_trace`.
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct SyscallTrace {
+    pub stats: [SyscallStats; MAX_SYSCALL_NUM],
+}
+
+impl SyscallTrace {
+    /// A fresh trace with every syscall's accounting zeroed.
+    pub fn new() -> Self {
+        Self {
+            stats: [SyscallStats::default(); MAX_SYSCALL_NUM],
+        }
+    }
+
+    /// Record one invocation of `syscall_id`, folding `elapsed_us` into its
+    /// cumulative time and remembering `args` as its most recent call.
+    pub fn record(&mut self, syscall_id: usize, args: [usize; 4], elapsed_us: u64) {
+        if let Some(entry) = self.stats.get_mut(syscall_id) {
+            entry.count += 1;
+            entry.total_time_us += elapsed_us;
+            entry.last_args = args;
+        }
+    }
+}
+
+impl Default for SyscallTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,11 +3,201 @@
 //! This  defines a set of utility functions that can be used in rcore
 //!
 
-use core::{mem::size_of, ptr::addr_of};
+use core::{
+    mem::{align_of, size_of, MaybeUninit},
+    ptr::addr_of,
+};
 
 use alloc::vec::Vec;
 
-use crate::{mm::translated_byte_buffer, task::current_user_token};
+use crate::{
+    config::TRAP_CONTEXT_BASE,
+    mm::{translated_byte_buffer, PageTable, VirtAddr, VirtPageNum},
+    task::current_user_token,
+};
+
+/// Why a user-space copy-in/copy-out was rejected before it ever touched the
+/// user page table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyError {
+    /// `[ptr, ptr + size_of::<T>())` reaches into kernel-only address space,
+    /// or overflows past the top of the address space
+    OutOfRange,
+    /// `ptr` is not aligned to `align_of::<T>()`
+    Misaligned,
+    /// Some page in the validated range isn't mapped at all
+    NotMapped,
+    /// Every page in the range is mapped, but at least one is missing the
+    /// permission bit the access requires
+    PermissionDenied,
+}
+
+/// Walk every page in `[ptr, ptr+len)`, failing unless each one is mapped in
+/// `token`'s page table, carries `need_write`'s required permission bit, and
+/// the whole range stays below kernel-only address space.
+fn validate_range(token: usize, ptr: usize, len: usize, need_write: bool) -> Result<(), CopyError> {
+    if len == 0 {
+        return Ok(());
+    }
+    let end = ptr.checked_add(len).ok_or(CopyError::OutOfRange)?;
+    if end > TRAP_CONTEXT_BASE {
+        return Err(CopyError::OutOfRange);
+    }
+    let page_table = PageTable::from_token(token);
+    let start_vpn = VirtAddr::from(ptr).floor();
+    let end_vpn = VirtAddr::from(end).ceil();
+    for vpn in start_vpn.0..end_vpn.0 {
+        let pte = page_table
+            .translate(VirtPageNum(vpn))
+            .filter(|pte| pte.is_valid())
+            .ok_or(CopyError::NotMapped)?;
+        let has_permission = if need_write { pte.writable() } else { pte.readable() };
+        if !has_permission {
+            return Err(CopyError::PermissionDenied);
+        }
+    }
+    Ok(())
+}
+
+/// A read-only, permission-checked, scatter-gather view of a `[ptr, len)`
+/// range in the current task's address space. Every page in the range is
+/// validated once, at construction, rather than on each access — the same
+/// contract Tock OS's `UserSlice` types give capsules. The underlying pages
+/// may not be contiguous in physical memory, so the range is only exposed as
+/// chunk-by-chunk fragments, never a single contiguous slice.
+pub struct UserSlice {
+    chunks: Vec<&'static mut [u8]>,
+}
+
+impl UserSlice {
+    /// Validate that `[ptr, ptr+len)` maps entirely to readable pages owned
+    /// by the current task, then gather it into scattered chunks.
+    pub fn new(ptr: *const u8, len: usize) -> Result<Self, CopyError> {
+        let token = current_user_token();
+        validate_range(token, ptr as usize, len, false)?;
+        Ok(Self {
+            chunks: translated_byte_buffer(token, ptr, len),
+        })
+    }
+
+    /// Iterate over this range's frame fragments in order
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> {
+        self.chunks.iter().map(|chunk| &chunk[..])
+    }
+
+    /// Copy this range out into a freshly allocated, contiguous `Vec<u8>`
+    pub fn copy_out(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.chunks.iter().map(|chunk| chunk.len()).sum());
+        for chunk in self.chunks() {
+            out.extend_from_slice(chunk);
+        }
+        out
+    }
+}
+
+/// A writable, permission-checked, scatter-gather view of a `[ptr, len)`
+/// range in the current task's address space. See [`UserSlice`] for the
+/// validation contract; this variant requires the W permission bit instead
+/// of R.
+pub struct UserSliceMut {
+    chunks: Vec<&'static mut [u8]>,
+}
+
+impl UserSliceMut {
+    /// Validate that `[ptr, ptr+len)` maps entirely to writable pages owned
+    /// by the current task, then gather it into scattered chunks.
+    pub fn new(ptr: *mut u8, len: usize) -> Result<Self, CopyError> {
+        let token = current_user_token();
+        validate_range(token, ptr as usize, len, true)?;
+        Ok(Self {
+            chunks: translated_byte_buffer(token, ptr, len),
+        })
+    }
+
+    /// Iterate over this range's frame fragments in order
+    pub fn chunks_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        self.chunks.iter_mut().map(|chunk| &mut chunk[..])
+    }
+
+    /// Copy `src` into this range chunk-by-chunk, failing without writing
+    /// anything short of `src` if the validated range is shorter than `src`.
+    pub fn copy_in(&mut self, src: &[u8]) -> Result<(), CopyError> {
+        if src.len() > self.chunks.iter().map(|chunk| chunk.len()).sum() {
+            return Err(CopyError::OutOfRange);
+        }
+        let mut src = src;
+        for chunk in self.chunks_mut() {
+            let nbytes = chunk.len().min(src.len());
+            chunk[..nbytes].copy_from_slice(&src[..nbytes]);
+            src = &src[nbytes..];
+        }
+        Ok(())
+    }
+}
+
+/// An incremental, partial-write view over a [`UserSliceMut`], modeled on
+/// std's `BorrowedBuf`/`BorrowedCursor`: a source that only has part of a
+/// read ready (a pipe or console returning fewer bytes than the buffer
+/// asked for) can commit it in one or more bounded steps via [`Self::append`]
+/// and report back exactly how many bytes landed, without ever re-exposing
+/// the still-uninitialized tail of the user region.
+pub struct UserBorrowedBuf {
+    chunks: Vec<&'static mut [u8]>,
+    filled: usize,
+}
+
+impl UserBorrowedBuf {
+    /// Wrap `slice`, starting with nothing filled.
+    pub fn new(slice: UserSliceMut) -> Self {
+        Self {
+            chunks: slice.chunks,
+            filled: 0,
+        }
+    }
+
+    /// Total capacity across every chunk of the wrapped range.
+    pub fn capacity(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.len()).sum()
+    }
+
+    /// Bytes committed so far.
+    pub fn filled(&self) -> usize {
+        self.filled
+    }
+
+    /// Bytes still unfilled.
+    pub fn remaining(&self) -> usize {
+        self.capacity() - self.filled
+    }
+
+    /// Commit as much of `src` as fits into the unfilled tail of this
+    /// buffer, advancing the cursor by that amount and returning it. Never
+    /// writes past `remaining()`; a `src` longer than `remaining()` is
+    /// truncated rather than causing an error, since a short destination is
+    /// the caller's to detect via the returned count.
+    pub fn append(&mut self, src: &[u8]) -> usize {
+        let src = &src[..src.len().min(self.remaining())];
+        let committed = src.len();
+        let mut skip = self.filled;
+        let mut src = src;
+        for chunk in self.chunks.iter_mut() {
+            if skip >= chunk.len() {
+                skip -= chunk.len();
+                continue;
+            }
+            let start = skip;
+            skip = 0;
+            let nbytes = (chunk.len() - start).min(src.len());
+            chunk[start..start + nbytes].copy_from_slice(&src[..nbytes]);
+            src = &src[nbytes..];
+            if src.is_empty() {
+                break;
+            }
+        }
+        self.filled += committed;
+        committed
+    }
+}
 
 /// User space ptr wrapper but correctly supply `write` and `read` interface.
 #[repr(transparent)]
@@ -21,11 +211,11 @@ impl<T: Sized> UserSpacePtr<T> {
     /// See [`core::ptr::write`] for safety concerns and examples.
     ///
     /// [`core::ptr::write`]: core::ptr::write()
-    pub unsafe fn write(self, val: T)
+    pub unsafe fn write(self, val: T) -> Result<(), CopyError>
     where
         T: Sized,
     {
-        let buffers = self.into_buffers();
+        let buffers = self.into_buffers(true)?;
         let mut src = unsafe { core::slice::from_raw_parts(addr_of!(val) as _, size_of::<T>()) };
 
         assert_eq!(src.len(), buffers.iter().map(|v| v.len()).sum());
@@ -34,7 +224,8 @@ impl<T: Sized> UserSpacePtr<T> {
             buffer.copy_from_slice(&src[..nbytes]);
             src = &src[nbytes..];
         }
-        assert_eq!(src.len(), 0)
+        assert_eq!(src.len(), 0);
+        Ok(())
     }
 
     /// Reads the value from `self` without moving it. This leaves the
@@ -43,15 +234,51 @@ impl<T: Sized> UserSpacePtr<T> {
     /// See [`core::ptr::read`] for safety concerns and examples.
     ///
     /// [`core::ptr::read`]: core::ptr::read()
-    pub unsafe fn read(self) -> T
+    pub unsafe fn read(self) -> Result<T, CopyError>
     where
         T: Sized,
     {
-        todo!()
+        let buffers = self.into_buffers(false)?;
+
+        let mut image = MaybeUninit::<T>::uninit();
+        let mut dst = unsafe {
+            core::slice::from_raw_parts_mut(image.as_mut_ptr() as *mut u8, size_of::<T>())
+        };
+
+        assert_eq!(dst.len(), buffers.iter().map(|v| v.len()).sum());
+        for buffer in buffers {
+            let nbytes = buffer.len();
+            dst[..nbytes].copy_from_slice(buffer);
+            dst = &mut dst[nbytes..];
+        }
+        assert_eq!(dst.len(), 0);
+
+        Ok(unsafe { image.assume_init() })
     }
 
-    fn into_buffers(self) -> Vec<&'static mut [u8]> {
-        translated_byte_buffer(current_user_token(), self.0 as _, size_of::<T>())
+    /// Validate `[ptr, ptr + size_of::<T>())` the way SGX's "UserSafe"
+    /// pointers do before ever touching it: reject a range that isn't fully
+    /// below kernel-only address space, that wraps around the address
+    /// space, or that isn't aligned to `align_of::<T>()`. Just like
+    /// [`UserSlice::new`]/[`UserSliceMut::new`], the range must also already
+    /// be mapped with the right permission bit (W when `need_write`, R
+    /// otherwise) — an aligned, in-range but
+    /// unmapped pointer is rejected here rather than panicking inside
+    /// [`translated_byte_buffer`]. Only once all of that holds does this
+    /// translate the range through the current task's page table into
+    /// scattered `&mut [u8]` slices.
+    fn into_buffers(self, need_write: bool) -> Result<Vec<&'static mut [u8]>, CopyError> {
+        let ptr = self.0 as usize;
+        if ptr % align_of::<T>() != 0 {
+            return Err(CopyError::Misaligned);
+        }
+        let end = ptr.checked_add(size_of::<T>()).ok_or(CopyError::OutOfRange)?;
+        if end > TRAP_CONTEXT_BASE {
+            return Err(CopyError::OutOfRange);
+        }
+        let token = current_user_token();
+        validate_range(token, ptr, size_of::<T>(), need_write)?;
+        Ok(translated_byte_buffer(token, self.0 as _, size_of::<T>()))
     }
 }
 